@@ -0,0 +1,316 @@
+use rgrit_core::{
+    Bgr555, BitDepth, Color, Compression, DataType, GfxFormat, MapLayout, MapReduction,
+    Transparency,
+};
+use rgrit_rs::BitmapBuilder;
+use std::str::FromStr;
+
+pub const USAGE: &str = "\
+Usage: rgrit <inputs> [options]
+
+Graphics:
+  -g, -g!           Export the graphics, or exclude them
+  -gb, -gt          Bitmap or tiled graphics
+  -gB{n}            Bit depth (1, 2, 4, 8, 16) or texture format (a3i5, a5i3, 4x4)
+  -gx               NDS texture, 2 to 16bpp or a texture format
+  -gT{n}, -gT!      Transparent color as RRGGBB or 16-bit BGR hex, or no transparency
+  -ga{n}            Offset added to non-zero pixel values
+  -gz{c}            Graphics compression
+  -gu{n}            Graphics data type (8, 16, 32)
+
+Palette:
+  -p, -p!           Export the palette, or exclude it
+  -ps{n}, -pe{n}    First and one-past-last palette entry
+  -pn{n}            Number of palette entries
+  -pz{c}            Palette compression
+  -pu{n}            Palette data type (8, 16, 32)
+  -pS               Share one palette between all inputs
+
+Map:
+  -m, -m!           Export a map, or exclude it
+  -mR{t,p,f}, -mR!  Map reduction (tiles, palettes, flips) or none
+  -mL{f,s,a}        Map layout (flat, screenblock, affine)
+  -mB{n}:{fields}   Map entry format, e.g. 16:i10h1v1p4 (index, flips, palette, _ unused)
+  -ma{n}            Offset added to non-zero map entries
+  -mz{c}            Map compression
+  -mu{n}            Map data type (8, 16, 32)
+
+Area and tiling:
+  -al{n}, -ar{n}, -aw{n}   Area left, right and width
+  -at{n}, -ab{n}, -ah{n}   Area top, bottom and height
+  -tw{n}, -th{n}           Tile width and height
+  -Mw{n}, -Mh{n}           Metatile width and height in tiles
+
+Output:
+  -ft{b,c,s,r,rs}   Raw binary, C, GAS assembly (default), GRF or Rust source
+  -fh, -fh!         Write a C header next to C and GAS output, or don't
+  -o{name}          Output file name without extension
+  -s{name}          Symbol name
+  -O{name}          Output file name for shared data
+  -S{name}          Symbol name for shared data
+  -Z{c}             Compression for all sections
+  -U{n}             Data type for all sections
+  --preview <file>  Also render the converted data back to a PNG
+
+Compression {c} is one of ! (off), 0 (off with header), l (LZ77), h (Huffman) or r (RLE).
+";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FileType {
+    Binary,
+    C,
+    /// The default, like grit's.
+    #[default]
+    Gas,
+    /// One GRF file per image, shared palettes are copied into each.
+    Grf,
+    Rust,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Args {
+    pub inputs: Vec<String>,
+    pub settings: BitmapBuilder,
+    pub file_type: FileType,
+    pub output: Option<String>,
+    pub symbol: Option<String>,
+    pub shared_palette: bool,
+    pub shared_output: Option<String>,
+    pub shared_symbol: Option<String>,
+    pub header: bool,
+    pub preview: Option<String>,
+    pub help: bool,
+}
+
+impl Args {
+    /// Parses grit style arguments, e.g. `-gB4`, `-mRtf` or `-ftb`.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+        let mut parsed = Args {
+            header: true,
+            ..Default::default()
+        };
+        let mut settings = BitmapBuilder::default();
+        let mut map = false;
+        let mut map_layout = None;
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let Some(flag) = arg.strip_prefix('-') else {
+                parsed.inputs.push(arg);
+                continue;
+            };
+
+            settings = match flag {
+                "h" | "-help" => {
+                    parsed.help = true;
+                    settings
+                }
+                "gb" => settings.with_format(GfxFormat::Bitmap),
+                "gt" => settings.with_format(GfxFormat::Tile),
+                "gT!" => settings.with_transparency(Transparency::Disabled),
+                "gx" => settings.with_texture(true),
+                "m" => {
+                    map = true;
+                    settings
+                }
+                "g" | "g!" => settings.with_gfx(flag == "g"),
+                "p" | "p!" => settings.with_palette(flag == "p"),
+                "m!" => settings.with_map(false),
+                "-preview" => {
+                    let path = args.next().ok_or("--preview: expected a file name")?;
+                    parsed.preview = Some(path);
+                    settings
+                }
+                "fh" | "fh!" => {
+                    parsed.header = flag == "fh";
+                    settings
+                }
+                // Warning levels only affect grit's logging
+                _ if flag.starts_with('W') => settings,
+                "pS" => {
+                    parsed.shared_palette = true;
+                    settings
+                }
+                _ if flag.starts_with("gB") => {
+                    settings.with_bit_depth_override(bit_depth(&flag[2..])?)
+                }
+                _ if flag.starts_with("gT") => {
+                    settings.with_transparency(Transparency::Color(color(&flag[2..])?))
+                }
+                _ if flag.starts_with("gz") => {
+                    settings.with_gfx_compression(compression(&flag[2..])?)
+                }
+                _ if flag.starts_with("pz") => {
+                    settings.with_palette_compression(compression(&flag[2..])?)
+                }
+                _ if flag.starts_with("mz") => {
+                    settings.with_map_compression(compression(&flag[2..])?)
+                }
+                _ if flag.starts_with("ga") => settings.with_gfx_offset(number(flag, 2)?),
+                _ if flag.starts_with("ma") => settings.with_map_offset(number(flag, 2)?),
+                _ if flag.starts_with("gu") => settings.with_gfx_data_type(data_type(flag, 2)?),
+                _ if flag.starts_with("pu") => settings.with_palette_data_type(data_type(flag, 2)?),
+                _ if flag.starts_with("mu") => settings.with_map_data_type(data_type(flag, 2)?),
+                _ if flag.starts_with('U') => settings.with_data_type(data_type(flag, 1)?),
+                _ if flag.starts_with('Z') => settings.with_compression(compression(&flag[1..])?),
+                _ if flag.starts_with("ps") => settings.with_palette_start(number(flag, 2)?),
+                _ if flag.starts_with("pe") => settings.with_palette_end(number(flag, 2)?),
+                _ if flag.starts_with("pn") => settings.with_palette_count(number(flag, 2)?),
+                _ if flag.starts_with("mR") => {
+                    map = true;
+                    settings.with_map_reduction(map_reduction(&flag[2..])?)
+                }
+                _ if flag.starts_with("mB") => {
+                    map = true;
+                    let format = flag[2..].parse().map_err(|e| format!("-{flag}: {e}"))?;
+                    settings.with_map_entry_format(format)
+                }
+                _ if flag.starts_with("mL") => {
+                    map_layout = Some(layout(&flag[2..])?);
+                    settings
+                }
+                _ if flag.starts_with("al") => settings.with_area_left(number(flag, 2)?),
+                _ if flag.starts_with("ar") => settings.with_area_right(number(flag, 2)?),
+                _ if flag.starts_with("aw") => settings.with_area_width(number(flag, 2)?),
+                _ if flag.starts_with("at") => settings.with_area_top(number(flag, 2)?),
+                _ if flag.starts_with("ab") => settings.with_area_bottom(number(flag, 2)?),
+                _ if flag.starts_with("ah") => settings.with_area_height(number(flag, 2)?),
+                _ if flag.starts_with("tw") => settings.with_tile_width(number(flag, 2)?),
+                _ if flag.starts_with("th") => settings.with_tile_height(number(flag, 2)?),
+                _ if flag.starts_with("Mw") => settings.with_meta_width(number(flag, 2)?),
+                _ if flag.starts_with("Mh") => settings.with_meta_height(number(flag, 2)?),
+                _ if flag.starts_with("ft") => {
+                    parsed.file_type = file_type(&flag[2..])?;
+                    settings
+                }
+                _ if flag.starts_with('o') => {
+                    parsed.output = Some(flag[1..].to_string());
+                    settings
+                }
+                _ if flag.starts_with('s') => {
+                    parsed.symbol = Some(flag[1..].to_string());
+                    settings
+                }
+                _ if flag.starts_with('O') => {
+                    parsed.shared_output = Some(flag[1..].to_string());
+                    settings
+                }
+                _ if flag.starts_with('S') => {
+                    parsed.shared_symbol = Some(flag[1..].to_string());
+                    settings
+                }
+                _ => return Err(format!("unknown option: {arg}")),
+            };
+        }
+
+        // A map is exported as soon as any map option is given, like grit does
+        if map || map_layout.is_some() {
+            settings = settings.with_map_layout(map_layout.unwrap_or_default());
+        }
+
+        parsed.settings = settings;
+        Ok(parsed)
+    }
+}
+
+fn number<T: FromStr>(flag: &str, prefix: usize) -> Result<T, String> {
+    flag[prefix..]
+        .parse()
+        .map_err(|_| format!("-{flag}: expected a number after -{}", &flag[..prefix]))
+}
+
+fn data_type(flag: &str, prefix: usize) -> Result<DataType, String> {
+    match &flag[prefix..] {
+        "8" => Ok(DataType::U8),
+        "16" => Ok(DataType::U16),
+        "32" => Ok(DataType::U32),
+        _ => Err(format!("-{flag}: unknown data type, expected 8, 16 or 32")),
+    }
+}
+
+fn bit_depth(value: &str) -> Result<BitDepth, String> {
+    match value {
+        "a3i5" | "A3I5" => Ok(BitDepth::A3I5),
+        "a5i3" | "A5I3" => Ok(BitDepth::A5I3),
+        "4x4" => Ok(BitDepth::FourByFour),
+        _ => value
+            .parse()
+            .map(BitDepth::Custom)
+            .map_err(|_| format!("-gB{value}: unknown bit depth")),
+    }
+}
+
+fn color(value: &str) -> Result<Color, String> {
+    let hex = value.trim_start_matches("0x");
+    let clr = u32::from_str_radix(hex, 16).map_err(|_| format!("-gT{value}: expected hex"))?;
+
+    match hex.len() {
+        6 => Ok(Color::RGB {
+            r: (clr >> 16) as u8,
+            g: (clr >> 8) as u8,
+            b: clr as u8,
+        }),
+        1..=4 => Ok(Color::Bgr555(Bgr555(clr as u16))),
+        _ => Err(format!("-gT{value}: expected RRGGBB or a 16-bit color")),
+    }
+}
+
+fn compression(value: &str) -> Result<Compression, String> {
+    match value {
+        "!" => Ok(Compression::Off),
+        "0" => Ok(Compression::OffHeader),
+        "l" => Ok(Compression::LZ77),
+        "h" => Ok(Compression::Huffman),
+        "r" => Ok(Compression::RLE),
+        _ => Err(format!(
+            "{value}: unknown compression, expected !, 0, l, h or r"
+        )),
+    }
+}
+
+fn map_reduction(value: &str) -> Result<MapReduction, String> {
+    // grit's shorthands for the common combinations
+    match value {
+        "!" => return Ok(MapReduction::NONE),
+        "4" => return Ok(MapReduction::ALL),
+        "8" => return Ok(MapReduction::TILES_FLIPS),
+        "a" => return Ok(MapReduction::TILES),
+        _ => {}
+    }
+
+    let mut map_reduction = MapReduction::NONE;
+    for flag in value.chars() {
+        match flag {
+            't' => map_reduction.tiles = true,
+            'p' => map_reduction.palettes = true,
+            'f' => map_reduction.flips = true,
+            _ => return Err(format!("-mR{value}: unknown map reduction '{flag}'")),
+        }
+    }
+
+    Ok(map_reduction)
+}
+
+fn layout(value: &str) -> Result<MapLayout, String> {
+    match value {
+        "f" => Ok(MapLayout::Flat),
+        "s" => Ok(MapLayout::ScreenBlock),
+        "a" => Ok(MapLayout::Affine),
+        _ => Err(format!(
+            "-mL{value}: unknown map layout, expected f, s or a"
+        )),
+    }
+}
+
+fn file_type(value: &str) -> Result<FileType, String> {
+    match value {
+        "b" => Ok(FileType::Binary),
+        "c" => Ok(FileType::C),
+        "s" => Ok(FileType::Gas),
+        "r" => Ok(FileType::Grf),
+        "rs" => Ok(FileType::Rust),
+        _ => Err(format!(
+            "-ft{value}: unknown file type, expected b, c, s, r or rs"
+        )),
+    }
+}
@@ -0,0 +1,205 @@
+mod args;
+mod output;
+
+use args::{Args, FileType};
+use rgrit_core::decode;
+use rgrit_rs::{BatchBuilder, Bitmap};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("rgrit: {e}\n\n{}", args::USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if args.help || args.inputs.is_empty() {
+        print!("{}", args::USAGE);
+        return if args.help {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        };
+    }
+
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("rgrit: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    if args.output.is_some() && args.inputs.len() > 1 && !args.shared_palette {
+        return Err("-o can only be used with a single input, or together with -pS".into());
+    }
+
+    if args.shared_palette {
+        let batch = BatchBuilder::new(&args.inputs)
+            .with_settings(args.settings.clone())
+            .build()?;
+
+        for (input, bitmap) in args.inputs.iter().zip(&batch.bitmaps) {
+            warn(input, bitmap);
+            let (base, symbol) = names(input, None, args.symbol.as_deref());
+            if let FileType::Grf = args.file_type {
+                let bitmap = Bitmap {
+                    palette: batch.palette.clone(),
+                    ..bitmap.clone()
+                };
+                output::write_grf(&base, &bitmap)?;
+            } else {
+                let sections = output::sections(bitmap);
+                let rust = output::rust_bitmap(&symbol, bitmap);
+                output::write(
+                    args.file_type,
+                    &base,
+                    &symbol,
+                    &sections,
+                    &rust,
+                    args.header,
+                )?;
+            }
+
+            if let Some(path) = &args.preview {
+                preview(&preview_path(path, &symbol), bitmap, &batch.palette)?;
+            }
+        }
+
+        // Every GRF file already carries the palette
+        if let FileType::Grf = args.file_type {
+            return Ok(());
+        }
+
+        let shared = args
+            .shared_output
+            .as_deref()
+            .or(args.output.as_deref())
+            .unwrap_or("shared");
+        let (base, symbol) = names(shared, None, args.shared_symbol.as_deref());
+        let sections = [output::Section {
+            name: "Pal",
+            extension: "pal",
+            data: &batch.palette,
+            data_type: batch
+                .bitmaps
+                .first()
+                .map(|bitmap| bitmap.spec.palette_data_type)
+                .unwrap_or_default(),
+        }];
+        let rust = output::rust_palette(&symbol, &batch.palette);
+        output::write(
+            args.file_type,
+            &base,
+            &symbol,
+            &sections,
+            &rust,
+            args.header,
+        )?;
+
+        return Ok(());
+    }
+
+    let builders = args
+        .inputs
+        .iter()
+        .map(|input| args.settings.clone().with_input(input))
+        .collect::<Vec<_>>();
+
+    for (input, bitmap) in args.inputs.iter().zip(rgrit_rs::build_all(&builders)) {
+        let bitmap = bitmap?;
+        warn(input, &bitmap);
+        let (base, symbol) = names(input, args.output.as_deref(), args.symbol.as_deref());
+        if let FileType::Grf = args.file_type {
+            output::write_grf(&base, &bitmap)?;
+        } else {
+            let sections = output::sections(&bitmap);
+            let rust = output::rust_bitmap(&symbol, &bitmap);
+            output::write(
+                args.file_type,
+                &base,
+                &symbol,
+                &sections,
+                &rust,
+                args.header,
+            )?;
+        }
+
+        if let Some(path) = &args.preview {
+            let path = match args.inputs.len() {
+                1 => PathBuf::from(path),
+                _ => preview_path(path, &symbol),
+            };
+            preview(&path, &bitmap, &bitmap.palette)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints what grit logged while converting `input`.
+fn warn(input: &str, bitmap: &Bitmap) {
+    for warning in &bitmap.warnings {
+        eprintln!("rgrit: {input}: {warning}");
+    }
+}
+
+/// Renders `bitmap` with `palette` back to pixels and saves them as a PNG.
+fn preview(path: &Path, bitmap: &Bitmap, palette: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+    let pixels = decode::decode(
+        &bitmap.gfx,
+        palette,
+        &bitmap.map,
+        &bitmap.meta,
+        &bitmap.spec,
+    )?;
+
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, bitmap.spec.width, bitmap.spec.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&pixels)?;
+
+    Ok(())
+}
+
+/// With several inputs every preview gets the symbol name appended, e.g. `out_bg.png`.
+fn preview_path(path: &str, symbol: &str) -> PathBuf {
+    let path = Path::new(path);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path.extension().unwrap_or("png".as_ref()).to_string_lossy();
+    path.with_file_name(format!("{stem}_{symbol}.{extension}"))
+}
+
+/// Picks the output path (without extension) and symbol name for `input`.
+///
+/// Like grit, both default to the input's file name without its extension. Only the last
+/// extension is removed, so `hero.v2.png` becomes `hero.v2`.
+fn names(input: &str, output: Option<&str>, symbol: Option<&str>) -> (PathBuf, String) {
+    let base = match output {
+        Some(output) => PathBuf::from(output),
+        None => PathBuf::from(Path::new(input).file_name().unwrap_or_default()),
+    };
+    let base = base.with_file_name(base.file_stem().unwrap_or_default());
+
+    let symbol = symbol.map(str::to_string).unwrap_or_else(|| {
+        let stem = base.file_name().unwrap_or_default().to_string_lossy();
+        let mut symbol = stem
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect::<String>();
+        if symbol.starts_with(|c: char| c.is_ascii_digit()) || symbol.is_empty() {
+            symbol.insert(0, '_');
+        }
+        symbol
+    });
+
+    (base, symbol)
+}
@@ -0,0 +1,179 @@
+use crate::args::FileType;
+use rgrit_core::{DataType, GfxFormat};
+use rgrit_rs::{codegen, Bitmap};
+use std::fmt::Write as _;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// One exported array, e.g. `fooTiles` in `foo.img.bin`.
+pub struct Section<'a> {
+    pub name: &'static str,
+    pub extension: &'static str,
+    pub data: &'a [u8],
+    pub data_type: DataType,
+}
+
+impl Section<'_> {
+    fn c_type(&self) -> &'static str {
+        match self.data_type {
+            DataType::U8 => "unsigned char",
+            DataType::U16 => "unsigned short",
+            DataType::U32 => "unsigned int",
+        }
+    }
+
+    fn gas_directive(&self) -> &'static str {
+        match self.data_type {
+            DataType::U8 => ".byte",
+            DataType::U16 => ".hword",
+            DataType::U32 => ".word",
+        }
+    }
+
+    /// The little endian elements of the section as hex literals.
+    fn elements(&self) -> Vec<String> {
+        let size = self.data_type.size();
+        self.data
+            .chunks(size)
+            .map(|chunk| {
+                let value = chunk
+                    .iter()
+                    .rev()
+                    .fold(0u32, |value, byte| value << 8 | *byte as u32);
+                format!("0x{value:0width$X}", width = size * 2)
+            })
+            .collect()
+    }
+}
+
+pub fn sections(bitmap: &Bitmap) -> Vec<Section<'_>> {
+    let gfx_name = match bitmap.spec.format {
+        GfxFormat::Bitmap => "Bitmap",
+        GfxFormat::Tile => "Tiles",
+    };
+
+    let spec = &bitmap.spec;
+    [
+        (gfx_name, "img", &bitmap.gfx, spec.gfx_data_type),
+        ("Pal", "pal", &bitmap.palette, spec.palette_data_type),
+        ("Map", "map", &bitmap.map, spec.map_data_type),
+        ("MetaTiles", "meta", &bitmap.meta, spec.map_data_type),
+    ]
+    .into_iter()
+    .filter(|(_, _, data, _)| !data.is_empty())
+    .map(|(name, extension, data, data_type)| Section {
+        name,
+        extension,
+        data,
+        data_type,
+    })
+    .collect()
+}
+
+/// `base` with `extension` appended, dots already in the file name stay part of it.
+fn file(base: &Path, extension: &str) -> PathBuf {
+    let mut path = base.as_os_str().to_owned();
+    path.push(".");
+    path.push(extension);
+    path.into()
+}
+
+/// Writes `sections` next to `base` in the requested format.
+///
+/// `rust` is the item written for [`FileType::Rust`], the other formats only use the sections.
+pub fn write(
+    file_type: FileType,
+    base: &Path,
+    symbol: &str,
+    sections: &[Section],
+    rust: &str,
+    header: bool,
+) -> io::Result<()> {
+    match file_type {
+        FileType::Binary => {
+            for section in sections {
+                std::fs::write(
+                    file(base, &format!("{}.bin", section.extension)),
+                    section.data,
+                )?;
+            }
+        }
+        FileType::C => {
+            let mut out = String::from("// Generated by rgrit, do not edit.\n");
+            for section in sections {
+                let elements = section.elements();
+                writeln!(
+                    out,
+                    "\nconst {} {symbol}{}[{}] __attribute__((aligned(4))) = {{",
+                    section.c_type(),
+                    section.name,
+                    elements.len()
+                )
+                .unwrap();
+                for chunk in elements.chunks(16 / section.data_type.size()) {
+                    writeln!(out, "    {},", chunk.join(",")).unwrap();
+                }
+                out.push_str("};\n");
+            }
+            std::fs::write(file(base, "c"), out)?;
+        }
+        FileType::Gas => {
+            let mut out =
+                String::from("@ Generated by rgrit, do not edit.\n\n    .section .rodata\n");
+            for section in sections {
+                let name = format!("{symbol}{}", section.name);
+                writeln!(out, "\n    .align  2\n    .global {name}\n{name}:").unwrap();
+                let directive = section.gas_directive();
+                for chunk in section.elements().chunks(16 / section.data_type.size()) {
+                    writeln!(out, "    {directive} {}", chunk.join(",")).unwrap();
+                }
+            }
+            std::fs::write(file(base, "s"), out)?;
+        }
+        // GRF files bundle a whole bitmap, see [`write_grf`]
+        FileType::Grf => {}
+        FileType::Rust => {
+            let out = format!("// Generated by rgrit, do not edit.\n\n{rust}");
+            std::fs::write(file(base, "rs"), out)?;
+        }
+    }
+
+    if header && matches!(file_type, FileType::C | FileType::Gas) {
+        let guard = format!("GRIT_{}_H", symbol.to_uppercase());
+        let mut out =
+            format!("// Generated by rgrit, do not edit.\n\n#ifndef {guard}\n#define {guard}\n");
+        for section in sections {
+            let name = format!("{symbol}{}", section.name);
+            let len = section.data.len();
+            let count = len / section.data_type.size();
+            writeln!(
+                out,
+                "\n#define {name}Len {len}\nextern const {} {name}[{count}];",
+                section.c_type()
+            )
+            .unwrap();
+        }
+        writeln!(out, "\n#endif // {guard}").unwrap();
+        std::fs::write(file(base, "h"), out)?;
+    }
+
+    Ok(())
+}
+
+/// Writes `bitmap` as `base.grf`.
+pub fn write_grf(base: &Path, bitmap: &Bitmap) -> io::Result<()> {
+    std::fs::write(file(base, "grf"), bitmap.to_grf())
+}
+
+/// The Rust item for a converted bitmap.
+pub fn rust_bitmap(symbol: &str, bitmap: &Bitmap) -> String {
+    codegen::source(codegen::static_bitmap(&symbol.to_uppercase(), bitmap))
+}
+
+/// The Rust item for a palette shared by several bitmaps.
+pub fn rust_palette(symbol: &str, palette: &[u8]) -> String {
+    codegen::source(codegen::static_bytes(
+        &format!("{}_PAL", symbol.to_uppercase()),
+        palette,
+    ))
+}
@@ -0,0 +1,115 @@
+//! The 15-bit colors used by GBA and NDS palettes.
+
+/// A 15-bit BGR color as stored in palette RAM, `0bAbbbbbgggggrrrrr`.
+///
+/// The top bit is ignored by the GBA and marks opaque pixels in NDS direct color bitmaps and
+/// textures.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct Bgr555(pub u16);
+
+impl Bgr555 {
+    pub const BLACK: Bgr555 = Bgr555(0);
+    pub const WHITE: Bgr555 = Bgr555(0x7FFF);
+    /// The NDS alpha bit.
+    pub const ALPHA: u16 = 0x8000;
+
+    /// Creates a color from 5-bit channels, higher bits are discarded.
+    pub const fn new(r: u8, g: u8, b: u8) -> Bgr555 {
+        Bgr555((r as u16 & 0x1F) | (g as u16 & 0x1F) << 5 | (b as u16 & 0x1F) << 10)
+    }
+
+    /// Converts from 8-bit channels by dropping the low 3 bits of each, like grit does.
+    pub const fn from_rgb888(r: u8, g: u8, b: u8) -> Bgr555 {
+        Bgr555::new(r >> 3, g >> 3, b >> 3)
+    }
+
+    /// Converts to 8-bit channels, mapping 31 to 255.
+    pub const fn to_rgb888(self) -> (u8, u8, u8) {
+        const fn scale(channel: u8) -> u8 {
+            (channel as u16 * 255 / 31) as u8
+        }
+        (scale(self.r()), scale(self.g()), scale(self.b()))
+    }
+
+    /// The red channel, 0 to 31.
+    pub const fn r(self) -> u8 {
+        (self.0 & 0x1F) as u8
+    }
+
+    /// The green channel, 0 to 31.
+    pub const fn g(self) -> u8 {
+        (self.0 >> 5 & 0x1F) as u8
+    }
+
+    /// The blue channel, 0 to 31.
+    pub const fn b(self) -> u8 {
+        (self.0 >> 10 & 0x1F) as u8
+    }
+
+    pub const fn alpha(self) -> bool {
+        self.0 & Bgr555::ALPHA != 0
+    }
+
+    /// Sets or clears the alpha bit.
+    pub const fn with_alpha(self, alpha: bool) -> Bgr555 {
+        if alpha {
+            Bgr555(self.0 | Bgr555::ALPHA)
+        } else {
+            Bgr555(self.0 & !Bgr555::ALPHA)
+        }
+    }
+
+    pub const fn from_le_bytes(bytes: [u8; 2]) -> Bgr555 {
+        Bgr555(u16::from_le_bytes(bytes))
+    }
+
+    pub const fn to_le_bytes(self) -> [u8; 2] {
+        self.0.to_le_bytes()
+    }
+
+    /// Iterates the colors of an uncompressed palette section.
+    ///
+    /// A trailing odd byte is ignored.
+    pub fn iter_le(data: &[u8]) -> Palette<'_> {
+        Palette(data.chunks_exact(2))
+    }
+}
+
+impl From<u16> for Bgr555 {
+    fn from(value: u16) -> Self {
+        Bgr555(value)
+    }
+}
+
+impl From<Bgr555> for u16 {
+    fn from(value: Bgr555) -> Self {
+        value.0
+    }
+}
+
+/// An iterator over the [`Bgr555`] colors of a palette.
+#[derive(Clone, Debug)]
+pub struct Palette<'a>(core::slice::ChunksExact<'a, u8>);
+
+impl Iterator for Palette<'_> {
+    type Item = Bgr555;
+
+    fn next(&mut self) -> Option<Bgr555> {
+        self.0.next().map(|c| Bgr555::from_le_bytes([c[0], c[1]]))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for Palette<'_> {
+    fn next_back(&mut self) -> Option<Bgr555> {
+        self.0
+            .next_back()
+            .map(|c| Bgr555::from_le_bytes([c[0], c[1]]))
+    }
+}
+
+impl ExactSizeIterator for Palette<'_> {}
@@ -0,0 +1,684 @@
+//! Rendering converted data back to RGBA pixels, e.g. for previews or to check a conversion
+//! without running it on hardware.
+//!
+//! Needs the `alloc` feature.
+
+use crate::decompress::{self, DecompressError};
+use crate::{
+    Bgr555, BitDepth, BitmapSpec, Compression, GfxFormat, MapEntryFormat, MapLayout, Transparency,
+};
+use alloc::borrow::Cow;
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// A compressed section couldn't be decompressed.
+    Decompress(DecompressError),
+    /// The named section is too short for the image.
+    Truncated(&'static str),
+    /// A pixel uses a palette entry that wasn't exported.
+    MissingColor(usize),
+    /// The bit depth isn't one of 1, 2, 4, 8 or 16.
+    UnsupportedBitDepth(u8),
+    /// The named tile or metatile dimension is 0.
+    ZeroSize(&'static str),
+}
+
+impl core::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DecodeError::Decompress(e) => write!(f, "{e}"),
+            DecodeError::Truncated(section) => write!(f, "{section} data is too short"),
+            DecodeError::MissingColor(index) => {
+                write!(f, "palette entry {index} is used but wasn't exported")
+            }
+            DecodeError::UnsupportedBitDepth(bpp) => write!(f, "{bpp}bpp can't be decoded"),
+            DecodeError::ZeroSize(dimension) => write!(f, "{dimension} is 0"),
+        }
+    }
+}
+
+impl core::error::Error for DecodeError {}
+
+impl From<DecompressError> for DecodeError {
+    fn from(e: DecompressError) -> Self {
+        DecodeError::Decompress(e)
+    }
+}
+
+/// The size and tiling of a conversion, see [`BitmapSpec::shape`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Shape {
+    /// Width of the converted area in pixels.
+    pub width: usize,
+    /// Height of the converted area in pixels.
+    pub height: usize,
+    /// Bits per pixel of the graphics, ignored for texture formats.
+    pub bpp: u8,
+    pub tile_width: usize,
+    pub tile_height: usize,
+    /// Metatile width in tiles.
+    pub meta_width: usize,
+    /// Metatile height in tiles.
+    pub meta_height: usize,
+}
+
+impl Shape {
+    /// A shape with 8x8 tiles and no metatiles.
+    pub const fn new(width: usize, height: usize, bpp: u8) -> Shape {
+        Shape {
+            width,
+            height,
+            bpp,
+            tile_width: 8,
+            tile_height: 8,
+            meta_width: 1,
+            meta_height: 1,
+        }
+    }
+
+    /// Sets the tile size in pixels, [`decode`] rejects 0.
+    pub const fn with_tile_size(mut self, width: usize, height: usize) -> Self {
+        self.tile_width = width;
+        self.tile_height = height;
+        self
+    }
+
+    /// Sets the metatile size in tiles, [`decode`] rejects 0.
+    pub const fn with_meta_size(mut self, width: usize, height: usize) -> Self {
+        self.meta_width = width;
+        self.meta_height = height;
+        self
+    }
+}
+
+/// Renders converted sections to `width * height` RGBA pixels.
+///
+/// The size and tiling come from `spec`. Compressed sections are decompressed first.
+/// Transparent pixels come out as `[0, 0, 0, 0]`. Tile and metatile sizes of 0 are an error.
+pub fn decode(
+    gfx: &[u8],
+    palette: &[u8],
+    map: &[u8],
+    meta: &[u8],
+    spec: &BitmapSpec,
+) -> Result<Vec<u8>, DecodeError> {
+    let shape = spec.shape();
+    for (size, dimension) in [
+        (shape.tile_width, "tile width"),
+        (shape.tile_height, "tile height"),
+        (shape.meta_width, "metatile width"),
+        (shape.meta_height, "metatile height"),
+    ] {
+        if size == 0 {
+            return Err(DecodeError::ZeroSize(dimension));
+        }
+    }
+
+    let gfx = section(gfx, spec.gfx_compression)?;
+    let palette = section(palette, spec.palette_compression)?;
+    let map = section(map, spec.map_compression)?;
+    let meta = section(meta, spec.map_compression)?;
+
+    let colors = Colors {
+        palette: Bgr555::iter_le(&palette).collect(),
+        start: spec.palette_range.map_or(0, |range| range.start as usize),
+        transparent: !matches!(spec.transparency, Transparency::Disabled),
+        key: match spec.transparency {
+            Transparency::Color(color) => Some(color.to_bgr555()),
+            _ => None,
+        },
+    };
+
+    if let Some(BitDepth::FourByFour) = spec.bit_depth {
+        return four_by_four(&gfx, &colors, shape);
+    }
+
+    let bpp = match spec.bit_depth {
+        Some(BitDepth::A3I5 | BitDepth::A5I3) => 8,
+        _ => shape.bpp,
+    };
+    if !matches!(bpp, 1 | 2 | 4 | 8 | 16) {
+        return Err(DecodeError::UnsupportedBitDepth(bpp));
+    }
+
+    let tiles = Tiles {
+        shape,
+        map: &map,
+        meta: &meta,
+        layout: spec.map_layout,
+        format: spec.map_entry_format,
+        offset: spec.map_offset,
+    };
+    let mut out = vec![0; shape.width * shape.height * 4];
+    let texture = matches!(spec.bit_depth, Some(BitDepth::A3I5 | BitDepth::A5I3));
+
+    for (i, pixel) in out.chunks_exact_mut(4).enumerate() {
+        let (x, y) = (i % shape.width, i / shape.width);
+        let (position, bank) = match spec.format {
+            // Textures are always linear
+            GfxFormat::Tile if !texture => tiles.position(x, y)?,
+            _ => (i, 0),
+        };
+        let value = match texel(&gfx, bpp, position)? {
+            // Pixel offsets only apply to palette indices
+            value if value != 0 && bpp <= 8 && !texture => {
+                value.wrapping_sub(spec.gfx_offset as u16) & ((1 << bpp) - 1)
+            }
+            value => value,
+        };
+
+        let rgba = match spec.bit_depth {
+            // 3 bits of alpha and a 5 bit index, or the other way around
+            Some(BitDepth::A3I5) => colors.opaque(value as usize & 0x1F)?.alpha(value >> 5, 7),
+            Some(BitDepth::A5I3) => colors.opaque(value as usize & 0x7)?.alpha(value >> 3, 31),
+            _ if bpp == 16 => colors.direct(Bgr555(value)),
+            // Index 0 is transparent, in every palette bank for 4bpp
+            _ if value == 0 && colors.transparent => Rgba::TRANSPARENT,
+            _ if bpp == 4 => colors.opaque(bank as usize * 16 + value as usize)?,
+            _ => colors.opaque(value as usize)?,
+        };
+        pixel.copy_from_slice(&rgba.0);
+    }
+
+    Ok(out)
+}
+
+/// Returns the data of a section without its compression header.
+fn section(data: &[u8], compression: Compression) -> Result<Cow<'_, [u8]>, DecodeError> {
+    match compression {
+        _ if data.is_empty() => Ok(Cow::Borrowed(data)),
+        Compression::Off => Ok(Cow::Borrowed(data)),
+        _ => {
+            let header = decompress::Header::parse(data)?;
+            let mut out = vec![0; header.size];
+            decompress::decompress(data, &mut out)?;
+            Ok(Cow::Owned(out))
+        }
+    }
+}
+
+/// Reads pixel number `position` from packed graphics, low bits first.
+fn texel(gfx: &[u8], bpp: u8, position: usize) -> Result<u16, DecodeError> {
+    let bit = position * bpp as usize;
+    let byte = bit / 8;
+
+    if bpp == 16 {
+        return read_u16(gfx, byte / 2, "graphics");
+    }
+
+    let value = *gfx.get(byte).ok_or(DecodeError::Truncated("graphics"))?;
+    Ok((value >> (bit % 8)) as u16 & ((1 << bpp) - 1))
+}
+
+/// Reads entry number `index` of `size` bytes.
+fn read_entry(
+    data: &[u8],
+    index: usize,
+    size: usize,
+    section: &'static str,
+) -> Result<u32, DecodeError> {
+    data.get(index * size..(index + 1) * size)
+        .map(|b| {
+            b.iter()
+                .rev()
+                .fold(0, |entry, byte| entry << 8 | *byte as u32)
+        })
+        .ok_or(DecodeError::Truncated(section))
+}
+
+fn read_u16(data: &[u8], index: usize, section: &'static str) -> Result<u16, DecodeError> {
+    data.get(index * 2..index * 2 + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or(DecodeError::Truncated(section))
+}
+
+#[derive(Clone, Copy)]
+struct Rgba([u8; 4]);
+
+impl Rgba {
+    const TRANSPARENT: Rgba = Rgba([0; 4]);
+
+    fn opaque(color: Bgr555) -> Rgba {
+        let (r, g, b) = color.to_rgb888();
+        Rgba([r, g, b, 0xFF])
+    }
+
+    /// Replaces the alpha with `alpha` out of `max`.
+    fn alpha(mut self, alpha: u16, max: u16) -> Rgba {
+        self.0[3] = (alpha * 255 / max) as u8;
+        self
+    }
+}
+
+struct Colors {
+    palette: Vec<Bgr555>,
+    /// The palette entry the exported palette starts at.
+    start: usize,
+    /// Whether palette entry 0 is transparent.
+    transparent: bool,
+    /// The color that is transparent in 16bpp graphics.
+    key: Option<Bgr555>,
+}
+
+impl Colors {
+    fn get(&self, index: usize) -> Result<Bgr555, DecodeError> {
+        index
+            .checked_sub(self.start)
+            .and_then(|i| self.palette.get(i))
+            .copied()
+            .ok_or(DecodeError::MissingColor(index))
+    }
+
+    fn opaque(&self, index: usize) -> Result<Rgba, DecodeError> {
+        self.get(index).map(Rgba::opaque)
+    }
+
+    /// A 16bpp color, which is transparent if it matches the transparent color.
+    fn direct(&self, color: Bgr555) -> Rgba {
+        let color = color.with_alpha(false);
+        match self.key {
+            Some(key) if key.with_alpha(false) == color => Rgba::TRANSPARENT,
+            _ => Rgba::opaque(color),
+        }
+    }
+}
+
+/// Finds pixels in tiled graphics, going through the map and metatiles if there are any.
+struct Tiles<'a> {
+    shape: Shape,
+    map: &'a [u8],
+    meta: &'a [u8],
+    layout: MapLayout,
+    format: MapEntryFormat,
+    /// The map offset, which non-zero entries carry on top of their tile.
+    offset: u32,
+}
+
+impl Tiles<'_> {
+    /// Returns the position of pixel `(x, y)` in the graphics and its 16 color palette bank.
+    fn position(&self, x: usize, y: usize) -> Result<(usize, u16), DecodeError> {
+        let Shape {
+            width,
+            height,
+            tile_width,
+            tile_height,
+            meta_width,
+            meta_height,
+            ..
+        } = self.shape;
+        let (tx, ty) = (x / tile_width, y / tile_height);
+        let (mut px, mut py) = (x % tile_width, y % tile_height);
+        let columns = width.div_ceil(tile_width);
+        let rows = height.div_ceil(tile_height);
+        let meta_columns = columns.div_ceil(meta_width);
+        let meta_size = meta_width * meta_height;
+        // Index of the tile within its metatile
+        let within = (ty % meta_height) * meta_width + tx % meta_width;
+
+        let size = self.format.size();
+        let entry = if self.map.is_empty() {
+            // Without a map the tiles are stored metatile by metatile
+            let tile = ((ty / meta_height) * meta_columns + tx / meta_width) * meta_size + within;
+            return Ok((tile * tile_width * tile_height + py * tile_width + px, 0));
+        } else if !self.meta.is_empty() {
+            let metatile = read_u16(
+                self.map,
+                (ty / meta_height) * meta_columns + tx / meta_width,
+                "map",
+            )?;
+            read_entry(
+                self.meta,
+                metatile as usize * meta_size + within,
+                size,
+                "metatile",
+            )?
+        } else {
+            read_entry(
+                self.map,
+                self.entry_index(tx, ty, columns, rows),
+                size,
+                "map",
+            )?
+        };
+
+        let entry = match entry {
+            0 => 0,
+            entry => entry.wrapping_sub(self.offset),
+        };
+
+        let format = &self.format;
+        if format.hflip.get(entry) != 0 {
+            px = tile_width - 1 - px;
+        }
+        if format.vflip.get(entry) != 0 {
+            py = tile_height - 1 - py;
+        }
+        let (tile, bank) = (
+            format.index.get(entry) as usize,
+            format.palette.get(entry) as u16,
+        );
+
+        Ok((tile * tile_width * tile_height + py * tile_width + px, bank))
+    }
+
+    /// Index of the map entry for tile `(tx, ty)`, screenblock layouts store the map in blocks of
+    /// 32x32 entries.
+    fn entry_index(&self, tx: usize, ty: usize, columns: usize, rows: usize) -> usize {
+        match self.layout {
+            MapLayout::ScreenBlock => {
+                let (block_width, block_height) = (columns.min(32), rows.min(32));
+                let block = (ty / block_height) * columns.div_ceil(block_width) + tx / block_width;
+                block * block_width * block_height
+                    + (ty % block_height) * block_width
+                    + tx % block_width
+            }
+            _ => ty * columns + tx,
+        }
+    }
+}
+
+/// Decodes the NDS 4x4 compressed texture format.
+///
+/// grit stores the texel blocks first, followed by one 16-bit palette index per block.
+fn four_by_four(gfx: &[u8], colors: &Colors, shape: Shape) -> Result<Vec<u8>, DecodeError> {
+    let columns = shape.width.div_ceil(4);
+    let blocks = columns * shape.height.div_ceil(4);
+    let (texels, indices) = gfx
+        .split_at_checked(blocks * 4)
+        .ok_or(DecodeError::Truncated("graphics"))?;
+    let mut out = vec![0; shape.width * shape.height * 4];
+
+    for (i, pixel) in out.chunks_exact_mut(4).enumerate() {
+        let (x, y) = (i % shape.width, i / shape.width);
+        let block = (y / 4) * columns + x / 4;
+        let texel = (texels[block * 4 + y % 4] >> ((x % 4) * 2)) & 3;
+        let index = read_u16(indices, block, "texture index")?;
+        // The offset counts pairs of colors
+        let base = (index & 0x3FFF) as usize * 2;
+        let color = |i: usize| colors.get(colors.start + base + i);
+
+        let rgba = match (index >> 14, texel) {
+            (0 | 1, 3) => Rgba::TRANSPARENT,
+            (1, 2) => Rgba::opaque(blend(color(0)?, color(1)?, 1, 1)),
+            (3, 2) => Rgba::opaque(blend(color(0)?, color(1)?, 5, 3)),
+            (3, 3) => Rgba::opaque(blend(color(0)?, color(1)?, 3, 5)),
+            (_, texel) => Rgba::opaque(color(texel as usize)?),
+        };
+        pixel.copy_from_slice(&rgba.0);
+    }
+
+    Ok(out)
+}
+
+/// Mixes two colors with the weights `wa` and `wb`, per 5 bit channel like the hardware does.
+fn blend(a: Bgr555, b: Bgr555, wa: u16, wb: u16) -> Bgr555 {
+    let mix = |a: u8, b: u8| ((a as u16 * wa + b as u16 * wb) / (wa + wb)) as u8;
+    Bgr555::new(mix(a.r(), b.r()), mix(a.g(), b.g()), mix(a.b(), b.b()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DataType, MapReduction, Sections};
+
+    /// An uncompressed, untiled and opaque spec.
+    fn spec(width: u32, height: u32, bpp: u8) -> BitmapSpec {
+        BitmapSpec {
+            bit_depth: Some(BitDepth::Custom(bpp)),
+            format: GfxFormat::Bitmap,
+            transparency: Transparency::Disabled,
+            palette_range: None,
+            map_reduction: MapReduction::NONE,
+            map_layout: MapLayout::Flat,
+            map_entry_format: MapEntryFormat::SCREEN_ENTRY,
+            gfx_compression: Compression::Off,
+            palette_compression: Compression::Off,
+            map_compression: Compression::Off,
+            gfx_data_type: DataType::default(),
+            palette_data_type: DataType::default(),
+            map_data_type: DataType::default(),
+            sections: Sections {
+                gfx: true,
+                palette: true,
+                map: true,
+            },
+            gfx_offset: 0,
+            map_offset: 0,
+            texture: None,
+            source_width: width,
+            source_height: height,
+            width,
+            height,
+            bpp,
+            tile_width: 8,
+            tile_height: 8,
+            meta_width: 1,
+            meta_height: 1,
+            tile_count: 0,
+            map_count: 0,
+            palette_count: 256,
+        }
+    }
+
+    /// A palette where every entry is a different color.
+    fn palette() -> Vec<u8> {
+        (1..=256u16).flat_map(|i| Bgr555(i).to_le_bytes()).collect()
+    }
+
+    fn color(index: u16) -> [u8; 4] {
+        Rgba::opaque(Bgr555(index + 1)).0
+    }
+
+    fn pixel(out: &[u8], width: usize, x: usize, y: usize) -> [u8; 4] {
+        let i = (y * width + x) * 4;
+        out[i..i + 4].try_into().unwrap()
+    }
+
+    /// Packs 4 bit values, low nibble first.
+    fn pack4(values: impl IntoIterator<Item = u8>) -> Vec<u8> {
+        let values = values.into_iter().collect::<Vec<_>>();
+        values
+            .chunks(2)
+            .map(|pair| pair[0] | pair[1] << 4)
+            .collect()
+    }
+
+    fn entries(entries: &[u16]) -> Vec<u8> {
+        entries
+            .iter()
+            .flat_map(|entry| entry.to_le_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn linear() {
+        let out = decode(&[0x21, 0x43], &palette(), &[], &[], &spec(4, 1, 4)).unwrap();
+        for x in 0..4 {
+            assert_eq!(pixel(&out, 4, x, 0), color(x as u16 + 1));
+        }
+
+        let gfx = (0..8).collect::<Vec<u8>>();
+        let out = decode(&gfx, &palette(), &[], &[], &spec(4, 2, 8)).unwrap();
+        for (i, value) in gfx.iter().enumerate() {
+            assert_eq!(pixel(&out, 4, i % 4, i / 4), color(*value as u16));
+        }
+    }
+
+    #[test]
+    fn transparent_index() {
+        let spec = BitmapSpec {
+            transparency: Transparency::FirstPaletteEntry,
+            ..spec(2, 1, 8)
+        };
+        let out = decode(&[0, 1], &palette(), &[], &[], &spec).unwrap();
+        assert_eq!(pixel(&out, 2, 0, 0), [0; 4]);
+        assert_eq!(pixel(&out, 2, 1, 0), color(1));
+    }
+
+    #[test]
+    fn tiles_with_flips_and_banks() {
+        // Tile 0 counts up to the right, tile 1 counts up downwards
+        let mut gfx = pack4((0..64).map(|i| i % 8 + 1));
+        gfx.extend(pack4((0..64).map(|i| i / 8 + 1)));
+        // Tile 0 flipped horizontally in bank 2, tile 1 flipped vertically in bank 0
+        let map = entries(&[0x2400, 0x0801]);
+        let spec = BitmapSpec {
+            format: GfxFormat::Tile,
+            ..spec(16, 8, 4)
+        };
+
+        let out = decode(&gfx, &palette(), &map, &[], &spec).unwrap();
+        for y in 0..8 {
+            for x in 0..8 {
+                assert_eq!(pixel(&out, 16, x, y), color(2 * 16 + 8 - x as u16));
+                assert_eq!(pixel(&out, 16, x + 8, y), color(8 - y as u16));
+            }
+        }
+    }
+
+    #[test]
+    fn screenblocks() {
+        // 64x32 tiles, the right half of the map is the second screenblock
+        let (width, height) = (512, 256);
+        let mut gfx = vec![0; 64];
+        gfx.extend([1; 64]);
+        let mut map = vec![0; 64 * 32];
+        map[32 * 32] = 1;
+        let spec = BitmapSpec {
+            format: GfxFormat::Tile,
+            map_layout: MapLayout::ScreenBlock,
+            ..spec(width, height, 8)
+        };
+
+        let out = decode(&gfx, &palette(), &entries(&map), &[], &spec).unwrap();
+        let width = width as usize;
+        for (y, x) in (0..height as usize).flat_map(|y| (0..width).map(move |x| (y, x))) {
+            let expected = if (x / 8, y / 8) == (32, 0) { 1 } else { 0 };
+            assert_eq!(pixel(&out, width, x, y), color(expected), "({x}, {y})");
+        }
+    }
+
+    #[test]
+    fn metatiles() {
+        // Tile t is filled with t + 1
+        let gfx = (0..4).flat_map(|t| [t + 1; 64]).collect::<Vec<u8>>();
+        let spec = BitmapSpec {
+            format: GfxFormat::Tile,
+            meta_width: 2,
+            meta_height: 2,
+            ..spec(16, 16, 8)
+        };
+
+        // One metatile with its tiles in reverse
+        let out = decode(
+            &gfx,
+            &palette(),
+            &entries(&[0]),
+            &entries(&[3, 2, 1, 0]),
+            &spec,
+        )
+        .unwrap();
+        for (tx, ty) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            let tile = 3 - (ty * 2 + tx) as u16;
+            assert_eq!(pixel(&out, 16, tx * 8, ty * 8), color(tile + 1));
+        }
+
+        // Without a map the tiles are stored metatile by metatile
+        let out = decode(&gfx, &palette(), &[], &[], &spec).unwrap();
+        for (tx, ty) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            let tile = (ty * 2 + tx) as u16;
+            assert_eq!(pixel(&out, 16, tx * 8 + 7, ty * 8 + 7), color(tile + 1));
+        }
+    }
+
+    #[test]
+    fn translucent_textures() {
+        let spec_a3i5 = BitmapSpec {
+            bit_depth: Some(BitDepth::A3I5),
+            ..spec(2, 1, 8)
+        };
+        let out = decode(&[7 << 5 | 3, 3 << 5 | 4], &palette(), &[], &[], &spec_a3i5).unwrap();
+        assert_eq!(pixel(&out, 2, 0, 0), color(3));
+        let [r, g, b, _] = color(4);
+        assert_eq!(pixel(&out, 2, 1, 0), [r, g, b, (3 * 255 / 7) as u8]);
+
+        let spec_a5i3 = BitmapSpec {
+            bit_depth: Some(BitDepth::A5I3),
+            ..spec(2, 1, 8)
+        };
+        let out = decode(&[31 << 3 | 2, 5], &palette(), &[], &[], &spec_a5i3).unwrap();
+        assert_eq!(pixel(&out, 2, 0, 0), color(2));
+        let [r, g, b, _] = color(5);
+        assert_eq!(pixel(&out, 2, 1, 0), [r, g, b, 0]);
+    }
+
+    #[test]
+    fn four_by_four_blocks() {
+        // Every row of both blocks uses texels 0, 1, 2 and 3
+        let mut gfx = vec![0b11_10_01_00; 8];
+        // Block 0 is plain with 3 colors, block 1 interpolates starting at the second pair
+        gfx.extend(entries(&[0x0000, 0x4001]));
+        let spec = BitmapSpec {
+            bit_depth: Some(BitDepth::FourByFour),
+            ..spec(8, 4, 8)
+        };
+
+        let out = decode(&gfx, &palette(), &[], &[], &spec).unwrap();
+        let middle = blend(Bgr555(3), Bgr555(4), 1, 1);
+        for y in 0..4 {
+            assert_eq!(pixel(&out, 8, 0, y), color(0));
+            assert_eq!(pixel(&out, 8, 1, y), color(1));
+            assert_eq!(pixel(&out, 8, 2, y), color(2));
+            assert_eq!(pixel(&out, 8, 3, y), [0; 4]);
+            assert_eq!(pixel(&out, 8, 4, y), color(2));
+            assert_eq!(pixel(&out, 8, 5, y), color(3));
+            assert_eq!(pixel(&out, 8, 6, y), Rgba::opaque(middle).0);
+            assert_eq!(pixel(&out, 8, 7, y), [0; 4]);
+        }
+    }
+
+    #[test]
+    fn zero_sizes() {
+        let tiled = BitmapSpec {
+            format: GfxFormat::Tile,
+            ..spec(8, 8, 8)
+        };
+        for (spec, dimension) in [
+            (
+                BitmapSpec {
+                    tile_width: 0,
+                    ..tiled
+                },
+                "tile width",
+            ),
+            (
+                BitmapSpec {
+                    tile_height: 0,
+                    ..tiled
+                },
+                "tile height",
+            ),
+            (
+                BitmapSpec {
+                    meta_width: 0,
+                    ..tiled
+                },
+                "metatile width",
+            ),
+            (
+                BitmapSpec {
+                    meta_height: 0,
+                    ..tiled
+                },
+                "metatile height",
+            ),
+        ] {
+            assert_eq!(
+                decode(&[0; 64], &palette(), &[], &[], &spec),
+                Err(DecodeError::ZeroSize(dimension))
+            );
+        }
+    }
+}
@@ -0,0 +1,247 @@
+//! Decoders for the GBA/NDS BIOS compression formats grit can emit.
+//!
+//! Every compressed section starts with a 4-byte little endian header. The high nibble of the
+//! first byte selects the format, the low nibble is a format specific parameter and the
+//! remaining 24 bits hold the decompressed size.
+
+use crate::Compression;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecompressError {
+    /// The input ended before the header said it would.
+    Truncated,
+    /// The output buffer is smaller than the decompressed size in the header.
+    OutputTooSmall { needed: usize },
+    /// The header names a format that isn't supported.
+    UnknownFormat(u8),
+    /// The header names a different format than the decoder that was called.
+    WrongFormat(Compression),
+    /// A back reference or tree node points outside of the data.
+    Corrupt,
+}
+
+impl core::fmt::Display for DecompressError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DecompressError::Truncated => write!(f, "compressed data is truncated"),
+            DecompressError::OutputTooSmall { needed } => {
+                write!(f, "output buffer is too small, {needed} bytes are needed")
+            }
+            DecompressError::UnknownFormat(tag) => {
+                write!(f, "{tag:#04x} is not a known compression format")
+            }
+            DecompressError::WrongFormat(compression) => {
+                write!(f, "data is compressed with {compression:?}")
+            }
+            DecompressError::Corrupt => write!(f, "compressed data is corrupt"),
+        }
+    }
+}
+
+impl core::error::Error for DecompressError {}
+
+/// The 4-byte header in front of compressed data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Header {
+    pub compression: Compression,
+    /// The low nibble of the type byte, the symbol size in bits for Huffman.
+    pub parameter: u8,
+    /// Size of the data after decompression.
+    pub size: usize,
+}
+
+impl Header {
+    pub fn parse(data: &[u8]) -> Result<Header, DecompressError> {
+        let [tag, a, b, c] = *data.first_chunk::<4>().ok_or(DecompressError::Truncated)?;
+
+        let compression = match tag >> 4 {
+            0 => Compression::OffHeader,
+            1 => Compression::LZ77,
+            2 => Compression::Huffman,
+            3 => Compression::RLE,
+            _ => return Err(DecompressError::UnknownFormat(tag)),
+        };
+
+        Ok(Header {
+            compression,
+            parameter: tag & 0xF,
+            size: u32::from_le_bytes([a, b, c, 0]) as usize,
+        })
+    }
+}
+
+/// Decompresses `data` into `out` based on its header and returns the decompressed size.
+pub fn decompress(data: &[u8], out: &mut [u8]) -> Result<usize, DecompressError> {
+    match Header::parse(data)?.compression {
+        Compression::LZ77 => decompress_lz77(data, out),
+        Compression::Huffman => decompress_huffman(data, out),
+        Compression::RLE => decompress_rle(data, out),
+        _ => {
+            let (header, data) = split(data, Compression::OffHeader)?;
+            let out = output(&header, out)?;
+            out.copy_from_slice(data.get(..header.size).ok_or(DecompressError::Truncated)?);
+            Ok(header.size)
+        }
+    }
+}
+
+/// Decompresses LZ77 (`0x10`) data into `out` and returns the decompressed size.
+pub fn decompress_lz77(data: &[u8], out: &mut [u8]) -> Result<usize, DecompressError> {
+    let (header, data) = split(data, Compression::LZ77)?;
+    let out = output(&header, out)?;
+    let mut input = data.iter().copied();
+    let mut next = || input.next().ok_or(DecompressError::Truncated);
+
+    let mut pos = 0;
+    while pos < out.len() {
+        let flags = next()?;
+
+        for bit in (0..8).rev() {
+            if pos >= out.len() {
+                break;
+            }
+
+            if flags & (1 << bit) == 0 {
+                out[pos] = next()?;
+                pos += 1;
+            } else {
+                let (hi, lo) = (next()?, next()?);
+                let length = (hi >> 4) as usize + 3;
+                let distance = (((hi & 0xF) as usize) << 8 | lo as usize) + 1;
+
+                if distance > pos {
+                    return Err(DecompressError::Corrupt);
+                }
+
+                // The source may overlap the destination, so this has to go byte by byte
+                for _ in 0..length.min(out.len() - pos) {
+                    out[pos] = out[pos - distance];
+                    pos += 1;
+                }
+            }
+        }
+    }
+
+    Ok(header.size)
+}
+
+/// Decompresses run-length encoded (`0x30`) data into `out` and returns the decompressed size.
+pub fn decompress_rle(data: &[u8], out: &mut [u8]) -> Result<usize, DecompressError> {
+    let (header, data) = split(data, Compression::RLE)?;
+    let out = output(&header, out)?;
+    let mut input = data.iter().copied();
+    let mut next = || input.next().ok_or(DecompressError::Truncated);
+
+    let mut pos = 0;
+    while pos < out.len() {
+        let flag = next()?;
+
+        if flag & 0x80 != 0 {
+            let length = (flag & 0x7F) as usize + 3;
+            let value = next()?;
+            let end = (pos + length).min(out.len());
+            out[pos..end].fill(value);
+            pos = end;
+        } else {
+            let length = (flag & 0x7F) as usize + 1;
+            for _ in 0..length.min(out.len() - pos) {
+                out[pos] = next()?;
+                pos += 1;
+            }
+        }
+    }
+
+    Ok(header.size)
+}
+
+/// Decompresses 4 or 8-bit Huffman (`0x24`/`0x28`) data into `out` and returns the
+/// decompressed size.
+pub fn decompress_huffman(data: &[u8], out: &mut [u8]) -> Result<usize, DecompressError> {
+    let (header, rest) = split(data, Compression::Huffman)?;
+    let out = output(&header, out)?;
+    let bits = match header.parameter {
+        4 => 4,
+        8 => 8,
+        _ => return Err(DecompressError::Corrupt),
+    };
+
+    // The tree starts with its own size, offsets are relative to the start of the header
+    let tree_size = (*rest.first().ok_or(DecompressError::Truncated)? as usize + 1) * 2;
+    let tree = data
+        .get(..4 + tree_size)
+        .ok_or(DecompressError::Truncated)?;
+    let stream = &data[4 + tree_size..];
+
+    const ROOT: usize = 5;
+    let node_at = |pos: usize| tree.get(pos).copied().ok_or(DecompressError::Corrupt);
+
+    let mut pos = ROOT;
+    let mut node = node_at(ROOT)?;
+    let mut written = 0;
+    let mut pending = 0u8;
+    let mut pending_bits = 0;
+
+    for word in stream.chunks(4) {
+        let mut bytes = [0; 4];
+        bytes[..word.len()].copy_from_slice(word);
+        let word = u32::from_le_bytes(bytes);
+
+        for bit in (0..32).rev() {
+            if written >= out.len() {
+                return Ok(header.size);
+            }
+
+            let right = (word >> bit) & 1 != 0;
+            let child = (pos & !1) + ((node & 0x3F) as usize) * 2 + 2 + right as usize;
+            let is_leaf = node & if right { 0x40 } else { 0x80 } != 0;
+
+            if !is_leaf {
+                pos = child;
+                node = node_at(child)?;
+                continue;
+            }
+
+            let symbol = node_at(child)?;
+            if bits == 8 {
+                out[written] = symbol;
+                written += 1;
+            } else {
+                // Symbols fill each byte starting with the low nibble
+                pending |= (symbol & 0xF) << pending_bits;
+                pending_bits += 4;
+                if pending_bits == 8 {
+                    out[written] = pending;
+                    written += 1;
+                    pending = 0;
+                    pending_bits = 0;
+                }
+            }
+
+            pos = ROOT;
+            node = node_at(ROOT)?;
+        }
+    }
+
+    if written < out.len() {
+        return Err(DecompressError::Truncated);
+    }
+
+    Ok(header.size)
+}
+
+fn split(data: &[u8], expected: Compression) -> Result<(Header, &[u8]), DecompressError> {
+    let header = Header::parse(data)?;
+
+    if header.compression != expected {
+        return Err(DecompressError::WrongFormat(header.compression));
+    }
+
+    Ok((header, &data[4..]))
+}
+
+fn output<'a>(header: &Header, out: &'a mut [u8]) -> Result<&'a mut [u8], DecompressError> {
+    out.get_mut(..header.size)
+        .ok_or(DecompressError::OutputTooSmall {
+            needed: header.size,
+        })
+}
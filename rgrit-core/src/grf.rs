@@ -0,0 +1,568 @@
+//! Reads GRF files, grit's RIFF container for a converted bitmap.
+//!
+//! A GRF file is a `RIFF` chunk of form `GRF ` holding a `HDR ` chunk and one chunk per exported
+//! section: `GFX `, `PAL ` and `MAP `, or `MTIL` for the metatiles and `MMAP` for the metatile
+//! map of metatiled bitmaps. Every section starts with the 4 byte header of its compression,
+//! which grit also writes for uncompressed data. Chunks are padded to 4 bytes, so sections stay
+//! word aligned as long as the file is.
+//!
+//! The `HDR ` chunk is grit's, all little endian:
+//!
+//! | Offset | Size | Field                                                               |
+//! |--------|------|---------------------------------------------------------------------|
+//! | 0      | 1    | Bits per pixel, or 128 plus the [`TextureFormat`] of a texture      |
+//! | 1      | 1    | Bits per map or metatile entry, 0 without a map                     |
+//! | 2      | 1    | Bits per metatile map entry, 0 without one                          |
+//! | 3      | 1    | Palette entry count, truncated to 8 bits so 256 entries read as 0   |
+//! | 4      | 4    | Tile width, tile height, metatile width and metatile height         |
+//! | 8      | 8    | Width and height in pixels as `u32`                                 |
+//!
+//! rgrit adds an `RGRT` chunk with the rest of the [`BitmapSpec`], which grit's loaders skip:
+//!
+//! | Offset | Size | Field                                                                     |
+//! |--------|------|---------------------------------------------------------------------------|
+//! | 0      | 2    | Transparent color as BGR555, `0xFFFF` if transparency is disabled         |
+//! | 2      | 4    | Palette range start and end as `u16`, both 0 without a range              |
+//! | 6      | 1    | Map reduction in bits 0-2 (tiles, flips, palettes), tiled format in bit 3 |
+//! |        |      | and the exported sections in bits 4-6 (graphics, palette, map)            |
+//! | 7      | 1    | Map layout: flat, screenblock or affine                                   |
+//! | 8      | 3    | Graphics, palette and map compression                                     |
+//! | 11     | 1    | Graphics, palette and map data type, 2 bits each                          |
+//! | 12     | 8    | Source width and height in pixels as `u32`                                |
+//! | 20     | 8    | Tile count and map entry count as `u32`                                   |
+//! | 28     | 2    | Palette entry count as `u16`                                              |
+//! | 30     | 1    | Bits per pixel, also for textures                                         |
+//! | 31     | 1    | Unused                                                                    |
+//! | 32     | 8    | Graphics and map offset as `u32`                                          |
+//! | 40     | 1    | Map entry size in bits                                                    |
+//! | 41     | 8    | Shift and width of the tile index, flips and palette bank in map entries  |
+//! | 49     | 3    | Unused                                                                    |
+//!
+//! Files without it, e.g. the ones grit writes, get the remaining fields from the sections or
+//! their defaults.
+
+use crate::{
+    Bgr555, BitDepth, BitmapSpec, Color, Compression, DataType, GfxFormat, MapEntryFormat,
+    MapField, MapLayout, MapReduction, Palette, PaletteRange, Sections, StaticBitmap, Texture,
+    TextureFormat, TextureSpec, Transparency,
+};
+
+/// Size of the `HDR ` chunk.
+pub const HEADER_SIZE: usize = 16;
+
+/// Size of the `RGRT` chunk.
+pub const SPEC_SIZE: usize = 52;
+
+/// Size of the compression header in front of every section.
+pub const DATA_HEADER_SIZE: usize = 4;
+
+const NO_TRANSPARENCY: u16 = 0xFFFF;
+const TEXTURE: u8 = 128;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GrfError {
+    /// The data isn't a `RIFF` chunk of form `GRF `.
+    NotGrf,
+    /// A chunk runs past the end of the data.
+    Truncated,
+    /// There is no `HDR ` chunk.
+    MissingHeader,
+    /// A header field holds a value that isn't known, named after the field.
+    BadHeader(&'static str),
+}
+
+impl core::fmt::Display for GrfError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            GrfError::NotGrf => write!(f, "data is not a GRF file"),
+            GrfError::Truncated => write!(f, "GRF file is truncated"),
+            GrfError::MissingHeader => write!(f, "GRF file has no header"),
+            GrfError::BadHeader(field) => write!(f, "GRF header has an invalid {field}"),
+        }
+    }
+}
+
+impl core::error::Error for GrfError {}
+
+/// Encodes the `HDR ` chunk for `spec`, `map` and `meta` tell whether those sections exist.
+pub fn header(spec: &BitmapSpec, map: bool, meta: bool) -> [u8; HEADER_SIZE] {
+    let mut out = [0; HEADER_SIZE];
+
+    out[0] = match spec.texture {
+        Some(texture) => TEXTURE + texture.format as u8,
+        None => spec.bpp,
+    };
+    // With metatiles the map holds the metatile indices and `meta` the entries
+    out[1] = if map || meta {
+        spec.map_entry_format.bits
+    } else {
+        0
+    };
+    out[2] = if map && meta { 16 } else { 0 };
+    out[3] = spec.palette_count as u8;
+    out[4..8].copy_from_slice(&[
+        spec.tile_width,
+        spec.tile_height,
+        spec.meta_width,
+        spec.meta_height,
+    ]);
+    out[8..12].copy_from_slice(&spec.width.to_le_bytes());
+    out[12..16].copy_from_slice(&spec.height.to_le_bytes());
+
+    out
+}
+
+/// Encodes the `RGRT` chunk for `spec`.
+pub fn spec(spec: &BitmapSpec) -> [u8; SPEC_SIZE] {
+    let mut out = [0; SPEC_SIZE];
+
+    let transparent = match spec.transparency {
+        Transparency::Color(color) => color.to_bgr555().0 & !Bgr555::ALPHA,
+        _ => NO_TRANSPARENCY,
+    };
+    out[0..2].copy_from_slice(&transparent.to_le_bytes());
+    if let Some(range) = spec.palette_range {
+        out[2..4].copy_from_slice(&range.start.to_le_bytes());
+        out[4..6].copy_from_slice(&range.end.to_le_bytes());
+    }
+
+    let MapReduction {
+        tiles,
+        flips,
+        palettes,
+    } = spec.map_reduction;
+    let tiled = matches!(spec.format, GfxFormat::Tile);
+    let Sections { gfx, palette, map } = spec.sections;
+    out[6] = tiles as u8
+        | (flips as u8) << 1
+        | (palettes as u8) << 2
+        | (tiled as u8) << 3
+        | (gfx as u8) << 4
+        | (palette as u8) << 5
+        | (map as u8) << 6;
+    out[7] = match spec.map_layout {
+        MapLayout::Flat => 0,
+        MapLayout::ScreenBlock => 1,
+        MapLayout::Affine => 2,
+    };
+
+    for (i, compression) in [
+        spec.gfx_compression,
+        spec.palette_compression,
+        spec.map_compression,
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        out[8 + i] = match compression {
+            Compression::Off => 0,
+            Compression::LZ77 => 1,
+            Compression::Huffman => 2,
+            Compression::RLE => 3,
+            Compression::OffHeader => 4,
+        };
+    }
+
+    out[11] = [
+        spec.gfx_data_type,
+        spec.palette_data_type,
+        spec.map_data_type,
+    ]
+    .into_iter()
+    .enumerate()
+    .fold(0, |bits, (i, data_type)| {
+        bits | (data_type.size().trailing_zeros() as u8) << (i * 2)
+    });
+
+    out[12..16].copy_from_slice(&spec.source_width.to_le_bytes());
+    out[16..20].copy_from_slice(&spec.source_height.to_le_bytes());
+    out[20..24].copy_from_slice(&spec.tile_count.to_le_bytes());
+    out[24..28].copy_from_slice(&spec.map_count.to_le_bytes());
+    out[28..30].copy_from_slice(&spec.palette_count.to_le_bytes());
+    out[30] = spec.bpp;
+    out[32..36].copy_from_slice(&spec.gfx_offset.to_le_bytes());
+    out[36..40].copy_from_slice(&spec.map_offset.to_le_bytes());
+
+    let entry = &spec.map_entry_format;
+    out[40] = entry.bits;
+    for (i, field) in [entry.index, entry.hflip, entry.vflip, entry.palette]
+        .into_iter()
+        .enumerate()
+    {
+        out[41 + i * 2] = field.shift;
+        out[42 + i * 2] = field.len;
+    }
+
+    out
+}
+
+/// The compression header grit puts in front of uncompressed sections of `size` bytes.
+pub const fn data_header(size: usize) -> [u8; DATA_HEADER_SIZE] {
+    let size = (size as u32).to_le_bytes();
+    [0, size[0], size[1], size[2]]
+}
+
+/// Decodes a `HDR ` chunk.
+///
+/// Fields grit doesn't write are filled with their defaults, [`parse_spec`] reads them from an
+/// `RGRT` chunk.
+pub fn parse_header(data: &[u8]) -> Result<BitmapSpec, GrfError> {
+    let data = data
+        .first_chunk::<HEADER_SIZE>()
+        .ok_or(GrfError::Truncated)?;
+    let u32_at =
+        |at: usize| u32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]]);
+
+    let (bit_depth, texture) = match data[0] {
+        attr if attr > TEXTURE => {
+            let format = match attr - TEXTURE {
+                1 => TextureFormat::A3I5,
+                2 => TextureFormat::Palette4,
+                3 => TextureFormat::Palette16,
+                4 => TextureFormat::Palette256,
+                5 => TextureFormat::FourByFour,
+                6 => TextureFormat::A5I3,
+                7 => TextureFormat::Direct,
+                _ => return Err(GrfError::BadHeader("texture format")),
+            };
+            let bit_depth = match format {
+                TextureFormat::A3I5 => BitDepth::A3I5,
+                TextureFormat::A5I3 => BitDepth::A5I3,
+                TextureFormat::FourByFour => BitDepth::FourByFour,
+                format => BitDepth::Custom(format.bpp() as u8),
+            };
+            (bit_depth, Some(format))
+        }
+        bpp @ (1 | 2 | 4 | 8 | 16) => (BitDepth::Custom(bpp), None),
+        _ => return Err(GrfError::BadHeader("bit depth")),
+    };
+    let bpp = match texture {
+        Some(format) => format.bpp() as u8,
+        None => data[0],
+    };
+
+    let (map_layout, map_entry_format) = match data[1] {
+        0 | 16 => (MapLayout::Flat, MapEntryFormat::SCREEN_ENTRY),
+        8 => (MapLayout::Affine, MapEntryFormat::AFFINE),
+        // Only custom entry formats are this wide, the `RGRT` chunk holds their fields
+        32 => (
+            MapLayout::Flat,
+            MapEntryFormat {
+                bits: 32,
+                ..MapEntryFormat::SCREEN_ENTRY
+            },
+        ),
+        _ => return Err(GrfError::BadHeader("map entry size")),
+    };
+
+    let (width, height) = (u32_at(8), u32_at(12));
+    let (tile_width, tile_height) = (data[4], data[5]);
+    // grit converts bitmaps as a single tile
+    let format = if (tile_width as u32, tile_height as u32) == (width, height) {
+        GfxFormat::Bitmap
+    } else {
+        GfxFormat::Tile
+    };
+
+    Ok(BitmapSpec {
+        bit_depth: Some(bit_depth),
+        format,
+        transparency: Transparency::Disabled,
+        palette_range: None,
+        map_reduction: MapReduction::NONE,
+        map_layout,
+        map_entry_format,
+        gfx_compression: Compression::Off,
+        palette_compression: Compression::Off,
+        map_compression: Compression::Off,
+        gfx_data_type: DataType::U8,
+        palette_data_type: DataType::U8,
+        map_data_type: DataType::U8,
+        sections: Sections {
+            gfx: true,
+            palette: data[3] != 0 || bpp <= 8,
+            map: data[1] != 0,
+        },
+        gfx_offset: 0,
+        map_offset: 0,
+        texture: texture.map(|format| TextureSpec {
+            format,
+            width: width as u16,
+            height: height as u16,
+            transparent: false,
+        }),
+        source_width: width,
+        source_height: height,
+        width,
+        height,
+        bpp,
+        tile_width,
+        tile_height,
+        meta_width: data[6],
+        meta_height: data[7],
+        tile_count: 0,
+        map_count: 0,
+        palette_count: match data[3] {
+            0 if bpp <= 8 => 256,
+            count => count as u16,
+        },
+    })
+}
+
+/// Decodes an `RGRT` chunk written by [`spec`] on top of the `spec` read from the `HDR ` chunk.
+pub fn parse_spec(data: &[u8], spec: &mut BitmapSpec) -> Result<(), GrfError> {
+    let data = data.first_chunk::<SPEC_SIZE>().ok_or(GrfError::Truncated)?;
+    let u16_at = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+    let u32_at =
+        |at: usize| u32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]]);
+
+    let transparency = match u16_at(0) {
+        NO_TRANSPARENCY => Transparency::Disabled,
+        color => Transparency::Color(Color::Bgr555(Bgr555(color))),
+    };
+    let palette_range = match (u16_at(2), u16_at(4)) {
+        (0, 0) => None,
+        (start, end) => Some(PaletteRange { start, end }),
+    };
+
+    let flags = data[6];
+    let map_layout = match data[7] {
+        0 => MapLayout::Flat,
+        1 => MapLayout::ScreenBlock,
+        2 => MapLayout::Affine,
+        _ => return Err(GrfError::BadHeader("map layout")),
+    };
+
+    let field = |i: usize| MapField::new(data[41 + i * 2], data[42 + i * 2]);
+    let map_entry_format = MapEntryFormat {
+        bits: data[40],
+        index: field(0),
+        hflip: field(1),
+        vflip: field(2),
+        palette: field(3),
+    };
+    map_entry_format
+        .validate()
+        .map_err(|_| GrfError::BadHeader("map entry format"))?;
+
+    let compression = |at: usize| match data[at] {
+        0 => Ok(Compression::Off),
+        1 => Ok(Compression::LZ77),
+        2 => Ok(Compression::Huffman),
+        3 => Ok(Compression::RLE),
+        4 => Ok(Compression::OffHeader),
+        _ => Err(GrfError::BadHeader("compression")),
+    };
+    let data_type = |i: usize| match data[11] >> (i * 2) & 0x3 {
+        0 => Ok(DataType::U8),
+        1 => Ok(DataType::U16),
+        2 => Ok(DataType::U32),
+        _ => Err(GrfError::BadHeader("data type")),
+    };
+
+    *spec = BitmapSpec {
+        format: match flags & 0x8 {
+            0 => GfxFormat::Bitmap,
+            _ => GfxFormat::Tile,
+        },
+        transparency,
+        palette_range,
+        map_reduction: MapReduction {
+            tiles: flags & 0x1 != 0,
+            flips: flags & 0x2 != 0,
+            palettes: flags & 0x4 != 0,
+        },
+        map_layout,
+        map_entry_format,
+        gfx_compression: compression(8)?,
+        palette_compression: compression(9)?,
+        map_compression: compression(10)?,
+        gfx_data_type: data_type(0)?,
+        palette_data_type: data_type(1)?,
+        map_data_type: data_type(2)?,
+        sections: Sections {
+            gfx: flags & 0x10 != 0,
+            palette: flags & 0x20 != 0,
+            map: flags & 0x40 != 0,
+        },
+        gfx_offset: u32_at(32),
+        map_offset: u32_at(36),
+        texture: spec.texture.map(|texture| TextureSpec {
+            transparent: texture.format.is_paletted()
+                && !matches!(transparency, Transparency::Disabled),
+            ..texture
+        }),
+        source_width: u32_at(12),
+        source_height: u32_at(16),
+        bpp: data[30],
+        tile_count: u32_at(20),
+        map_count: u32_at(24),
+        palette_count: u16_at(28),
+        ..*spec
+    };
+
+    Ok(())
+}
+
+/// A parsed GRF file, borrowing its sections from the file data.
+#[derive(Clone, Debug)]
+pub struct Grf<'a> {
+    pub gfx: &'a [u8],
+    pub palette: &'a [u8],
+    pub map: &'a [u8],
+    pub meta: &'a [u8],
+    pub spec: BitmapSpec,
+}
+
+impl<'a> Grf<'a> {
+    /// Parses a GRF file without copying any of it.
+    ///
+    /// Uncompressed sections come without their compression header. Unknown chunks are
+    /// skipped, missing sections are left empty.
+    pub fn parse(data: &'a [u8]) -> Result<Grf<'a>, GrfError> {
+        if !data.starts_with(b"RIFF") {
+            return Err(GrfError::NotGrf);
+        }
+        let Some((b"GRF ", mut chunks)) = chunk(data)?.body.split_first_chunk::<4>() else {
+            return Err(GrfError::NotGrf);
+        };
+
+        let (mut header, mut extension) = (None, None);
+        let (mut gfx, mut palette, mut map, mut meta): (&[u8], &[u8], &[u8], &[u8]) =
+            (&[], &[], &[], &[]);
+
+        while !chunks.is_empty() {
+            let chunk = chunk(chunks)?;
+            match &chunk.id {
+                b"HDR " => header = Some(chunk.body),
+                b"RGRT" => extension = Some(chunk.body),
+                b"GFX " => gfx = chunk.body,
+                b"PAL " => palette = chunk.body,
+                b"MAP " | b"MMAP" => map = chunk.body,
+                b"MTIL" => meta = chunk.body,
+                _ => {}
+            }
+            chunks = chunk.rest;
+        }
+
+        let mut spec = parse_header(header.ok_or(GrfError::MissingHeader)?)?;
+        let (gfx_size, palette_size, map_size);
+        match extension {
+            Some(extension) => {
+                parse_spec(extension, &mut spec)?;
+                (gfx_size, palette_size, map_size) = (0, 0, 0);
+            }
+            // Without the spec the sections tell how they are compressed and how large they are
+            None => {
+                (spec.gfx_compression, gfx_size) = data_compression(gfx)?;
+                (spec.palette_compression, palette_size) = data_compression(palette)?;
+                (spec.map_compression, map_size) = data_compression(map)?;
+            }
+        }
+
+        let gfx = section(gfx, spec.gfx_compression)?;
+        let palette = section(palette, spec.palette_compression)?;
+        let map = section(map, spec.map_compression)?;
+        let meta = section(meta, spec.map_compression)?;
+
+        if extension.is_none() {
+            spec.sections = Sections {
+                gfx: !gfx.is_empty(),
+                palette: !palette.is_empty(),
+                map: !map.is_empty(),
+            };
+            let tile_bits =
+                spec.tile_width as usize * spec.tile_height as usize * spec.bpp as usize;
+            spec.tile_count = (gfx_size * 8).checked_div(tile_bits).unwrap_or(0) as u32;
+            // The map holds metatile indices if there are metatiles
+            let entry_size = match meta.is_empty() {
+                true => spec.map_entry_format.size(),
+                false => 2,
+            };
+            spec.map_count = map_size.checked_div(entry_size).unwrap_or(0) as u32;
+            spec.palette_count = (palette_size / 2) as u16;
+        }
+
+        Ok(Grf {
+            gfx,
+            palette,
+            map,
+            meta,
+            spec,
+        })
+    }
+
+    /// The palette colors, only meaningful for uncompressed palettes.
+    pub fn palette(&self) -> Palette<'a> {
+        Bgr555::iter_le(self.palette)
+    }
+
+    /// The texel data, 4x4 index block and palette of an NDS texture, `None` for other bitmaps.
+    pub fn texture(&self) -> Option<Texture<'a>> {
+        Texture::new(&self.spec, self.gfx, self.palette)
+    }
+}
+
+impl Grf<'static> {
+    /// The file as a [`StaticBitmap`], e.g. for a GRF file pulled in with `include_bytes!`.
+    ///
+    /// The sections are only as aligned as the file, so wrap it in [`Aligned`](crate::Aligned)
+    /// to read them with [`StaticBitmap::gfx_u16`] and friends:
+    /// `Grf::parse(&Aligned(*include_bytes!("bg.grf")).0)`.
+    pub fn to_static(&self) -> StaticBitmap {
+        StaticBitmap {
+            gfx: self.gfx,
+            palette: self.palette,
+            map: self.map,
+            meta: self.meta,
+            spec: self.spec,
+        }
+    }
+}
+
+/// A RIFF chunk and the data behind it.
+struct Chunk<'a> {
+    id: [u8; 4],
+    body: &'a [u8],
+    rest: &'a [u8],
+}
+
+/// Splits off the chunk at the start of `data`.
+fn chunk(data: &[u8]) -> Result<Chunk<'_>, GrfError> {
+    let (id, rest) = data.split_first_chunk::<4>().ok_or(GrfError::Truncated)?;
+    let (size, rest) = rest.split_first_chunk::<4>().ok_or(GrfError::Truncated)?;
+    let size = u32::from_le_bytes(*size) as usize;
+
+    Ok(Chunk {
+        id: *id,
+        body: rest.get(..size).ok_or(GrfError::Truncated)?,
+        rest: rest.get(size.next_multiple_of(4)..).unwrap_or(&[]),
+    })
+}
+
+/// Reads the compression and uncompressed size from the header of a section.
+fn data_compression(data: &[u8]) -> Result<(Compression, usize), GrfError> {
+    if data.is_empty() {
+        return Ok((Compression::Off, 0));
+    }
+
+    let header = data.first_chunk::<4>().ok_or(GrfError::Truncated)?;
+    let size = u32::from_le_bytes([header[1], header[2], header[3], 0]) as usize;
+    let compression = match header[0] & 0xF0 {
+        0x00 => Compression::Off,
+        0x10 => Compression::LZ77,
+        0x20 => Compression::Huffman,
+        0x30 => Compression::RLE,
+        _ => return Err(GrfError::BadHeader("compression")),
+    };
+
+    Ok((compression, size))
+}
+
+/// Strips the compression header off an uncompressed section, compressed ones keep it.
+fn section(data: &[u8], compression: Compression) -> Result<&[u8], GrfError> {
+    match (data.is_empty(), compression) {
+        (false, Compression::Off) => data.get(DATA_HEADER_SIZE..).ok_or(GrfError::Truncated),
+        _ => Ok(data),
+    }
+}
@@ -2,6 +2,23 @@
 
 use core::fmt::Formatter;
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+mod color;
+#[cfg(feature = "alloc")]
+pub mod decode;
+pub mod decompress;
+pub mod grf;
+mod map_entry;
+mod sprite;
+mod texture;
+
+pub use color::{Bgr555, Palette};
+pub use map_entry::{MapEntryFormat, MapEntryFormatError, MapField};
+pub use sprite::{Frame, ObjMapping, StaticSpriteSheet};
+pub use texture::{Texture, TextureFormat, TextureSpec};
+
 #[derive(Clone)]
 pub struct StaticBitmap {
     pub gfx: &'static [u8],
@@ -11,7 +28,144 @@ pub struct StaticBitmap {
     pub spec: BitmapSpec,
 }
 
-#[derive(Clone, Copy, Debug, Default)]
+impl StaticBitmap {
+    /// The graphics data as halfwords, e.g. for 16bpp bitmaps or DMA16 copies.
+    ///
+    /// # Panics
+    ///
+    /// If the graphics aren't 2-byte aligned or their length is odd.
+    pub fn gfx_u16(&self) -> &'static [u16] {
+        cast_u16(self.gfx)
+    }
+
+    /// The graphics data as words, e.g. for DMA32 or `CpuFastSet` copies.
+    ///
+    /// # Panics
+    ///
+    /// If the graphics aren't 4-byte aligned or their length isn't a multiple of 4.
+    pub fn gfx_u32(&self) -> &'static [u32] {
+        cast_u32(self.gfx)
+    }
+
+    /// The palette colors, only meaningful for uncompressed palettes.
+    pub fn palette(&self) -> Palette<'static> {
+        Bgr555::iter_le(self.palette)
+    }
+
+    /// The palette as BGR555 halfwords.
+    ///
+    /// # Panics
+    ///
+    /// If the palette isn't 2-byte aligned or its length is odd.
+    pub fn palette_u16(&self) -> &'static [u16] {
+        cast_u16(self.palette)
+    }
+
+    /// The palette as words, e.g. for DMA32 or `CpuFastSet` copies to palette RAM.
+    ///
+    /// # Panics
+    ///
+    /// If the palette isn't 4-byte aligned or its length isn't a multiple of 4.
+    pub fn palette_u32(&self) -> &'static [u32] {
+        cast_u32(self.palette)
+    }
+
+    /// The map as 16-bit screen entries.
+    ///
+    /// # Panics
+    ///
+    /// If the map isn't 2-byte aligned or its length is odd.
+    pub fn map_u16(&self) -> &'static [u16] {
+        cast_u16(self.map)
+    }
+
+    /// The map as words, e.g. for 32-bit custom entries or DMA32 copies.
+    ///
+    /// # Panics
+    ///
+    /// If the map isn't 4-byte aligned or its length isn't a multiple of 4.
+    pub fn map_u32(&self) -> &'static [u32] {
+        cast_u32(self.map)
+    }
+
+    /// The texel data, 4x4 index block and palette of an NDS texture, `None` for other bitmaps.
+    pub fn texture(&self) -> Option<Texture<'static>> {
+        Texture::new(&self.spec, self.gfx, self.palette)
+    }
+
+    /// Renders the bitmap to RGBA pixels, see [`decode::decode`].
+    #[cfg(feature = "alloc")]
+    pub fn decode(&self) -> Result<alloc::vec::Vec<u8>, decode::DecodeError> {
+        decode::decode(self.gfx, self.palette, self.map, self.meta, &self.spec)
+    }
+}
+
+// Generated statics are wrapped in `Aligned` and padded to their data type, so these only fail
+// for hand-written `StaticBitmap`s and ones parsed from GRF files that aren't word aligned.
+fn cast_u16(data: &'static [u8]) -> &'static [u16] {
+    // SAFETY: every bit pattern is a valid u16
+    let (prefix, body, suffix) = unsafe { data.align_to::<u16>() };
+    assert!(
+        prefix.is_empty() && suffix.is_empty(),
+        "data is not 2-byte aligned or not a multiple of 2 bytes long"
+    );
+    body
+}
+
+fn cast_u32(data: &'static [u8]) -> &'static [u32] {
+    // SAFETY: every bit pattern is a valid u32
+    let (prefix, body, suffix) = unsafe { data.align_to::<u32>() };
+    assert!(
+        prefix.is_empty() && suffix.is_empty(),
+        "data is not 4-byte aligned or not a multiple of 4 bytes long"
+    );
+    body
+}
+
+/// Forces 4-byte alignment on the data it wraps.
+///
+/// Generated code keeps each section in a `static DATA: Aligned<[u8; N]>` and borrows `&DATA.0`,
+/// so the data is stored once and can be copied with DMA or `CpuFastSet`.
+#[repr(C, align(4))]
+pub struct Aligned<T: ?Sized>(pub T);
+
+/// The element type of an exported section, mirroring grit's `-gu`, `-pu` and `-mu` flags.
+///
+/// Sections are padded to a multiple of the element size.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DataType {
+    #[default]
+    U8,
+    U16,
+    U32,
+}
+
+impl DataType {
+    /// Size of one element in bytes.
+    pub const fn size(&self) -> usize {
+        match self {
+            DataType::U8 => 1,
+            DataType::U16 => 2,
+            DataType::U32 => 4,
+        }
+    }
+}
+
+/// A batch of bitmaps converted against one shared palette.
+#[derive(Clone)]
+pub struct StaticBatch {
+    pub palette: &'static [u8],
+    pub bitmaps: &'static [StaticBitmap],
+}
+
+impl StaticBatch {
+    /// The colors of the shared palette.
+    pub fn palette(&self) -> Palette<'static> {
+        Bgr555::iter_le(self.palette)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub enum Compression {
     #[default]
     Off,
@@ -24,13 +178,37 @@ pub enum Compression {
 #[derive(Clone, Copy, Debug)]
 pub enum Color {
     RGB { r: u8, g: u8, b: u8 },
-    GBR16(u16),
+    Bgr555(Bgr555),
+}
+
+impl Color {
+    /// The former `Color::GBR16` variant, which is now [`Color::Bgr555`].
+    ///
+    /// Only constructing still works this way, patterns have to match `Color::Bgr555` instead.
+    #[deprecated(note = "use `Color::Bgr555(Bgr555(color))` instead")]
+    #[allow(non_snake_case)]
+    pub const fn GBR16(color: u16) -> Color {
+        Color::Bgr555(Bgr555(color))
+    }
+
+    /// The color as it ends up in palette RAM.
+    pub const fn to_bgr555(self) -> Bgr555 {
+        match self {
+            Color::RGB { r, g, b } => Bgr555::from_rgb888(r, g, b),
+            Color::Bgr555(color) => color,
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
 pub enum Transparency {
     Disabled,
+    /// This color becomes palette entry 0 and is transparent (`-gT{color}`).
     Color(Color),
+    /// Palette entry 0 of the source image stays the transparent one.
+    FirstPaletteEntry,
+    /// The color of the top left pixel is the transparent one.
+    TopLeftPixel,
 }
 
 impl Default for Transparency {
@@ -51,11 +229,151 @@ pub enum BitDepth {
     Custom(u8),
 }
 
+/// Map reduction options, mirroring grit's `-mR` flags.
+///
+/// The default performs no reduction (`-mR!`). Flip and palette reduction only take effect
+/// together with tile reduction.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MapReduction {
+    /// Merge identical tiles (`-mRt`).
+    pub tiles: bool,
+    /// Merge horizontally or vertically flipped tiles and set the flip bits (`-mRf`).
+    pub flips: bool,
+    /// Merge tiles that only differ in their 16 color palette bank (`-mRp`).
+    pub palettes: bool,
+}
+
+impl MapReduction {
+    pub const NONE: MapReduction = MapReduction {
+        tiles: false,
+        flips: false,
+        palettes: false,
+    };
+    pub const TILES: MapReduction = MapReduction {
+        tiles: true,
+        flips: false,
+        palettes: false,
+    };
+    pub const TILES_FLIPS: MapReduction = MapReduction {
+        tiles: true,
+        flips: true,
+        palettes: false,
+    };
+    pub const ALL: MapReduction = MapReduction {
+        tiles: true,
+        flips: true,
+        palettes: true,
+    };
+
+    pub const fn is_none(&self) -> bool {
+        !self.tiles && !self.flips && !self.palettes
+    }
+}
+
+/// The order and entry format of the exported map, mirroring grit's `-mL` flags.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MapLayout {
+    /// A single row-major map (`-mLf`).
+    #[default]
+    Flat,
+    /// Split into 32x32 screenblocks for regular backgrounds (`-mLs`).
+    ScreenBlock,
+    /// 8-bit entries for affine backgrounds (`-mLa`).
+    Affine,
+}
+
+/// The palette entries `start..end` that a conversion exported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PaletteRange {
+    pub start: u16,
+    pub end: u16,
+}
+
+impl PaletteRange {
+    /// Number of palette entries in the range.
+    pub const fn len(&self) -> u16 {
+        self.end.saturating_sub(self.start)
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The 16 color palette bank the range starts in, as used by 4bpp graphics.
+    pub const fn bank(&self) -> u16 {
+        self.start / 16
+    }
+}
+
+/// The sections a conversion exports, mirroring grit's `-g!`, `-p!` and `-m!` exclusions.
+///
+/// Excluded sections come back empty.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sections {
+    pub gfx: bool,
+    pub palette: bool,
+    /// The map and its metatiles.
+    pub map: bool,
+}
+
+/// The settings a bitmap was converted with.
+///
+/// `bit_depth` holds the depth grit picked if none was requested, the fields after `texture`
+/// are the values grit settled on.
 #[derive(Clone, Copy, Debug)]
 pub struct BitmapSpec {
     pub bit_depth: Option<BitDepth>,
     pub format: GfxFormat,
     pub transparency: Transparency,
+    pub palette_range: Option<PaletteRange>,
+    pub map_reduction: MapReduction,
+    pub map_layout: MapLayout,
+    /// The bit layout of the map entries, either a custom one or the one of `map_layout`.
+    pub map_entry_format: MapEntryFormat,
+    pub gfx_compression: Compression,
+    pub palette_compression: Compression,
+    pub map_compression: Compression,
+    pub gfx_data_type: DataType,
+    pub palette_data_type: DataType,
+    pub map_data_type: DataType,
+    /// The sections that were exported, the others are empty.
+    pub sections: Sections,
+    /// Added to every non-zero pixel value (`-ga`).
+    pub gfx_offset: u32,
+    /// Added to every non-zero map entry, e.g. the first tile in a shared charblock (`-ma`).
+    pub map_offset: u32,
+    /// Set for NDS textures, see [`StaticBitmap::texture`].
+    pub texture: Option<TextureSpec>,
+    /// Size of the source image in pixels.
+    pub source_width: u32,
+    pub source_height: u32,
+    /// Size of the converted area in pixels.
+    pub width: u32,
+    pub height: u32,
+    /// Bits per pixel of the graphics.
+    pub bpp: u8,
+    pub tile_width: u8,
+    pub tile_height: u8,
+    /// Metatile width in tiles.
+    pub meta_width: u8,
+    /// Metatile height in tiles.
+    pub meta_height: u8,
+    /// Number of tiles in the graphics after map reduction, one per pixel for bitmaps.
+    pub tile_count: u32,
+    /// Number of map entries, 0 without a map.
+    pub map_count: u32,
+    /// Number of palette entries.
+    pub palette_count: u16,
+}
+
+impl BitmapSpec {
+    /// The size and tiling of the converted bitmap, which [`decode::decode`] renders with.
+    #[cfg(feature = "alloc")]
+    pub fn shape(&self) -> decode::Shape {
+        decode::Shape::new(self.width as usize, self.height as usize, self.bpp)
+            .with_tile_size(self.tile_width as usize, self.tile_height as usize)
+            .with_meta_size(self.meta_width as usize, self.meta_height as usize)
+    }
 }
 
 #[derive(Clone, Copy, Debug, Default)]
@@ -76,3 +394,12 @@ impl core::fmt::Debug for StaticBitmap {
             .finish()
     }
 }
+
+impl core::fmt::Debug for StaticBatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("StaticBatch")
+            .field("palette", &format_args!("[u8; {}]", self.palette.len()))
+            .field("bitmaps", &self.bitmaps)
+            .finish()
+    }
+}
@@ -0,0 +1,202 @@
+//! Bit layouts of map entries, see [`MapEntryFormat`].
+
+use core::str::FromStr;
+
+/// A bitfield within a map entry.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MapField {
+    /// Position of the lowest bit.
+    pub shift: u8,
+    /// Width in bits, 0 if the entry doesn't have the field.
+    pub len: u8,
+}
+
+impl MapField {
+    pub const NONE: MapField = MapField { shift: 0, len: 0 };
+
+    pub const fn new(shift: u8, len: u8) -> MapField {
+        MapField { shift, len }
+    }
+
+    /// The bits of the field within an entry, bits past the 32nd are left out.
+    pub const fn mask(&self) -> u32 {
+        let bits = match self.len {
+            0 => 0,
+            len if len >= 32 => u32::MAX,
+            len => (1 << len) - 1,
+        };
+        match bits.checked_shl(self.shift as u32) {
+            Some(mask) => mask,
+            None => 0,
+        }
+    }
+
+    /// Reads the field from `entry`.
+    pub const fn get(&self, entry: u32) -> u32 {
+        match (entry & self.mask()).checked_shr(self.shift as u32) {
+            Some(value) => value,
+            None => 0,
+        }
+    }
+}
+
+/// The bit layout of map entries, mirroring grit's `-mB` flag.
+///
+/// Bits that no field covers stay 0, e.g. for flags an engine fills in itself.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MapEntryFormat {
+    /// Size of an entry in bits, 8, 16 or 32.
+    pub bits: u8,
+    pub index: MapField,
+    pub hflip: MapField,
+    pub vflip: MapField,
+    /// The 16 color palette bank.
+    pub palette: MapField,
+}
+
+impl MapEntryFormat {
+    /// Regular background screen entries, a 10-bit tile index, flips and a palette bank.
+    pub const SCREEN_ENTRY: MapEntryFormat = MapEntryFormat {
+        bits: 16,
+        index: MapField::new(0, 10),
+        hflip: MapField::new(10, 1),
+        vflip: MapField::new(11, 1),
+        palette: MapField::new(12, 4),
+    };
+
+    /// Affine background entries, a plain 8-bit tile index.
+    pub const AFFINE: MapEntryFormat = MapEntryFormat {
+        bits: 8,
+        index: MapField::new(0, 8),
+        hflip: MapField::NONE,
+        vflip: MapField::NONE,
+        palette: MapField::NONE,
+    };
+
+    /// Size of an entry in bytes.
+    pub const fn size(&self) -> usize {
+        self.bits as usize / 8
+    }
+
+    /// Checks that the entry size is valid and that the fields fit into it without overlapping.
+    pub const fn validate(&self) -> Result<(), MapEntryFormatError> {
+        if !matches!(self.bits, 8 | 16 | 32) {
+            return Err(MapEntryFormatError::BadSize(self.bits));
+        }
+        if self.index.len == 0 {
+            return Err(MapEntryFormatError::NoIndex);
+        }
+
+        let fields = [self.index, self.hflip, self.vflip, self.palette];
+        let mut used = 0u32;
+        let mut i = 0;
+        while i < fields.len() {
+            let field = fields[i];
+            if field.shift as u32 + field.len as u32 > self.bits as u32 {
+                return Err(MapEntryFormatError::TooLong);
+            }
+            if used & field.mask() != 0 {
+                return Err(MapEntryFormatError::Overlap);
+            }
+            used |= field.mask();
+            i += 1;
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for MapEntryFormat {
+    fn default() -> Self {
+        MapEntryFormat::SCREEN_ENTRY
+    }
+}
+
+/// Parses grit's `-mB` notation, e.g. `16:i10h1v1p4` for [`MapEntryFormat::SCREEN_ENTRY`].
+///
+/// The entry size comes first, followed by the fields from the lowest bit up: `i` for the tile
+/// index, `h` and `v` for the flips, `p` for the palette bank and `_` for unused bits, each with
+/// its width in bits.
+impl FromStr for MapEntryFormat {
+    type Err = MapEntryFormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (bits, mut fields) = s.split_once(':').ok_or(MapEntryFormatError::Syntax)?;
+        let mut format = MapEntryFormat {
+            bits: bits.parse().map_err(|_| MapEntryFormatError::Syntax)?,
+            index: MapField::NONE,
+            hflip: MapField::NONE,
+            vflip: MapField::NONE,
+            palette: MapField::NONE,
+        };
+
+        let mut shift = 0u8;
+        while let Some(name) = fields.chars().next() {
+            fields = &fields[name.len_utf8()..];
+            let digits = fields
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(fields.len());
+            let len = fields[..digits]
+                .parse::<u8>()
+                .map_err(|_| MapEntryFormatError::Syntax)?;
+            fields = &fields[digits..];
+
+            let field = match name {
+                'i' => &mut format.index,
+                'h' => &mut format.hflip,
+                'v' => &mut format.vflip,
+                'p' => &mut format.palette,
+                '_' => {
+                    shift = shift.saturating_add(len);
+                    continue;
+                }
+                name => return Err(MapEntryFormatError::UnknownField(name)),
+            };
+            if field.len != 0 {
+                return Err(MapEntryFormatError::Overlap);
+            }
+            *field = MapField::new(shift, len);
+            shift = shift.saturating_add(len);
+        }
+
+        format.validate()?;
+        Ok(format)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MapEntryFormatError {
+    /// The text isn't `{bits}:{fields}`, or a field has no width.
+    Syntax,
+    /// A field letter other than `i`, `h`, `v`, `p` or `_`.
+    UnknownField(char),
+    /// The entry size isn't 8, 16 or 32 bits.
+    BadSize(u8),
+    /// The fields don't fit into the entry.
+    TooLong,
+    /// Two fields share bits, or a field is given twice.
+    Overlap,
+    /// There is no tile index.
+    NoIndex,
+}
+
+impl core::fmt::Display for MapEntryFormatError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            MapEntryFormatError::Syntax => {
+                write!(f, "expected the entry size and fields, e.g. 16:i10h1v1p4")
+            }
+            MapEntryFormatError::UnknownField(name) => {
+                write!(f, "{name} is not a field, expected i, h, v, p or _")
+            }
+            MapEntryFormatError::BadSize(bits) => {
+                write!(f, "entries can't be {bits} bits, expected 8, 16 or 32")
+            }
+            MapEntryFormatError::TooLong => write!(f, "the fields don't fit into the entry"),
+            MapEntryFormatError::Overlap => write!(f, "fields overlap"),
+            MapEntryFormatError::NoIndex => write!(f, "entries need a tile index"),
+        }
+    }
+}
+
+impl core::error::Error for MapEntryFormatError {}
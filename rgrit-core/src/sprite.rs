@@ -0,0 +1,93 @@
+//! Sprite sheets sliced into frames, see [`StaticSpriteSheet`].
+
+use crate::{Bgr555, BitmapSpec, Palette};
+use core::ops::Range;
+
+/// How OBJ tiles are laid out in VRAM, set by bit 6 of `DISPCNT`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ObjMapping {
+    /// Each frame's tiles follow each other, row by row.
+    #[default]
+    OneD,
+    /// Tiles form a grid 32 tile indices wide, each frame is a rectangle in it.
+    TwoD,
+}
+
+impl ObjMapping {
+    /// Width of a 2D mapping row in bytes, 32 4bpp tiles or 16 8bpp tiles.
+    pub const ROW_SIZE: usize = 1024;
+}
+
+/// A frame of a sprite sheet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Frame {
+    /// Index of the frame's top left tile within the sheet in [`Frame::TILE_UNIT`]s, the unit
+    /// of the tile number in OBJ attribute 2 on the GBA for 4bpp and 8bpp tiles alike.
+    ///
+    /// 8bpp tiles take up two units, so their indices are even. Add the tile index the sheet is
+    /// loaded at. On the NDS, divide by `boundary / 32` for a 1D mapping boundary above 32 bytes.
+    pub tile: u16,
+    /// Width in tiles.
+    pub width: u8,
+    /// Height in tiles.
+    pub height: u8,
+}
+
+impl Frame {
+    /// Size of the unit [`Frame::tile`] counts in bytes, one 4bpp tile.
+    pub const TILE_UNIT: usize = 32;
+
+    /// Offset of the frame's top left tile in bytes.
+    pub const fn offset(&self) -> usize {
+        self.tile as usize * Frame::TILE_UNIT
+    }
+
+    /// The bytes of all of the frame's tiles with 1D mapping, for `bpp` bit tiles.
+    ///
+    /// With 2D mapping this only covers the frame's first row of tiles and the tiles after it.
+    pub const fn range(&self, bpp: u8) -> Range<usize> {
+        let size = self.width as usize * self.height as usize * bpp as usize * 8;
+        self.offset()..self.offset() + size
+    }
+}
+
+/// A sprite sheet whose tiles are laid out for an [`ObjMapping`].
+#[derive(Clone)]
+pub struct StaticSpriteSheet {
+    /// All frames, ready to be copied to OBJ VRAM.
+    pub tiles: &'static [u8],
+    pub palette: &'static [u8],
+    pub frames: &'static [Frame],
+    pub mapping: ObjMapping,
+    /// The settings the whole sheet was converted with.
+    pub spec: BitmapSpec,
+}
+
+impl StaticSpriteSheet {
+    /// The tiles of frame `index`, `None` if there is no such frame or the sheet uses 2D
+    /// mapping, where frames aren't contiguous.
+    pub fn frame(&self, index: usize) -> Option<&'static [u8]> {
+        let frame = self.frames.get(index)?;
+        match self.mapping {
+            ObjMapping::OneD => self.tiles.get(frame.range(self.spec.bpp)),
+            ObjMapping::TwoD => None,
+        }
+    }
+
+    /// The palette colors, only meaningful for uncompressed palettes.
+    pub fn palette(&self) -> Palette<'static> {
+        Bgr555::iter_le(self.palette)
+    }
+}
+
+impl core::fmt::Debug for StaticSpriteSheet {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("StaticSpriteSheet")
+            .field("tiles", &format_args!("[u8; {}]", self.tiles.len()))
+            .field("palette", &format_args!("[u8; {}]", self.palette.len()))
+            .field("frames", &self.frames)
+            .field("mapping", &self.mapping)
+            .field("spec", &self.spec)
+            .finish()
+    }
+}
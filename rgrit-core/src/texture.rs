@@ -0,0 +1,107 @@
+//! NDS textures and their `TEXIMAGE_PARAM` bits.
+
+use crate::{BitmapSpec, Compression};
+
+/// The texture formats of the NDS 3D engine, with their `TEXIMAGE_PARAM` format value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum TextureFormat {
+    A3I5 = 1,
+    Palette4 = 2,
+    Palette16 = 3,
+    Palette256 = 4,
+    /// 4x4 texel compression, with a palette index block next to the texels.
+    FourByFour = 5,
+    A5I3 = 6,
+    Direct = 7,
+}
+
+impl TextureFormat {
+    /// Bits per texel.
+    pub const fn bpp(&self) -> usize {
+        match self {
+            TextureFormat::Palette4 | TextureFormat::FourByFour => 2,
+            TextureFormat::Palette16 => 4,
+            TextureFormat::A3I5 | TextureFormat::Palette256 | TextureFormat::A5I3 => 8,
+            TextureFormat::Direct => 16,
+        }
+    }
+
+    /// Whether texels index the palette directly, only these formats can make color 0
+    /// transparent.
+    pub const fn is_paletted(&self) -> bool {
+        matches!(
+            self,
+            TextureFormat::Palette4 | TextureFormat::Palette16 | TextureFormat::Palette256
+        )
+    }
+}
+
+/// The texture a bitmap was converted to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TextureSpec {
+    pub format: TextureFormat,
+    /// Width in texels, a power of two from 8 to 1024.
+    pub width: u16,
+    /// Height in texels, a power of two from 8 to 1024.
+    pub height: u16,
+    /// Palette entry 0 is transparent.
+    pub transparent: bool,
+}
+
+impl TextureSpec {
+    /// The size, format and color 0 transparency bits of `TEXIMAGE_PARAM`.
+    ///
+    /// The VRAM offset, repeat, flip and texture coordinate transformation bits are left to the
+    /// caller.
+    pub const fn param(&self) -> u32 {
+        size_bits(self.width) << 20
+            | size_bits(self.height) << 23
+            | (self.format as u32) << 26
+            | (self.transparent as u32) << 29
+    }
+
+    /// Size of the texel data in bytes.
+    pub const fn texel_size(&self) -> usize {
+        self.width as usize * self.height as usize * self.format.bpp() / 8
+    }
+}
+
+/// The sections of a converted texture, ready to be copied to texture and palette VRAM.
+#[derive(Clone, Copy, Debug)]
+pub struct Texture<'a> {
+    pub spec: TextureSpec,
+    pub texels: &'a [u8],
+    /// One 16-bit palette offset and mode per 4x4 block, only used by
+    /// [`TextureFormat::FourByFour`].
+    pub indices: &'a [u8],
+    pub palette: &'a [u8],
+}
+
+impl<'a> Texture<'a> {
+    /// Splits converted sections into a texture, `None` if `spec` has no texture.
+    ///
+    /// grit appends the 4x4 palette index block to the texels. It can only be split off
+    /// uncompressed graphics, otherwise `texels` holds both and `indices` is empty.
+    pub fn new(spec: &BitmapSpec, gfx: &'a [u8], palette: &'a [u8]) -> Option<Texture<'a>> {
+        let texture = spec.texture?;
+        let (texels, indices) = match (texture.format, spec.gfx_compression) {
+            (TextureFormat::FourByFour, Compression::Off) => {
+                gfx.split_at_checked(texture.texel_size())?
+            }
+            _ => (gfx, &[][..]),
+        };
+
+        Some(Texture {
+            spec: texture,
+            texels,
+            indices,
+            palette,
+        })
+    }
+}
+
+/// The 3 bit `TEXIMAGE_PARAM` size, where 0 is 8 texels.
+const fn size_bits(texels: u16) -> u32 {
+    texels.trailing_zeros().saturating_sub(3) & 0x7
+}
@@ -0,0 +1,223 @@
+use rgrit_core::grf::{self, Grf, GrfError};
+use rgrit_core::{
+    Bgr555, BitDepth, BitmapSpec, Color, Compression, DataType, GfxFormat, MapEntryFormat,
+    MapField, MapLayout, MapReduction, PaletteRange, Sections, Transparency,
+};
+
+fn spec() -> BitmapSpec {
+    BitmapSpec {
+        bit_depth: Some(BitDepth::Custom(4)),
+        format: GfxFormat::Tile,
+        transparency: Transparency::Color(Color::Bgr555(Bgr555(0x7C1F))),
+        palette_range: Some(PaletteRange { start: 16, end: 32 }),
+        map_reduction: MapReduction::TILES_FLIPS,
+        map_layout: MapLayout::ScreenBlock,
+        map_entry_format: MapEntryFormat::SCREEN_ENTRY,
+        gfx_compression: Compression::Off,
+        palette_compression: Compression::Off,
+        map_compression: Compression::Off,
+        gfx_data_type: DataType::U32,
+        palette_data_type: DataType::U16,
+        map_data_type: DataType::U16,
+        sections: Sections {
+            gfx: true,
+            palette: true,
+            map: true,
+        },
+        gfx_offset: 0,
+        map_offset: 3,
+        texture: None,
+        source_width: 20,
+        source_height: 16,
+        width: 16,
+        height: 16,
+        bpp: 4,
+        tile_width: 8,
+        tile_height: 8,
+        meta_width: 1,
+        meta_height: 1,
+        tile_count: 2,
+        map_count: 4,
+        palette_count: 16,
+    }
+}
+
+/// Builds a GRF file out of `chunks`, padding each to 4 bytes.
+fn riff(chunks: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+    let mut out = b"RIFF\0\0\0\0GRF ".to_vec();
+    for (id, data) in chunks {
+        out.extend_from_slice(*id);
+        out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        out.extend_from_slice(data);
+        out.resize(out.len().next_multiple_of(4), 0);
+    }
+    let size = (out.len() - 8) as u32;
+    out[4..8].copy_from_slice(&size.to_le_bytes());
+    out
+}
+
+/// An uncompressed section with the header grit puts in front of it.
+fn section(data: &[u8]) -> Vec<u8> {
+    [&grf::data_header(data.len())[..], data].concat()
+}
+
+#[test]
+fn header_matches_grit() {
+    let header = grf::header(&spec(), true, false);
+    assert_eq!(header, [4, 16, 0, 16, 8, 8, 1, 1, 16, 0, 0, 0, 16, 0, 0, 0]);
+
+    // 256 colors wrap to 0, metatiles move the map entry size
+    let spec = BitmapSpec {
+        palette_count: 256,
+        ..spec()
+    };
+    assert_eq!(grf::header(&spec, true, true)[..4], [4, 16, 16, 0]);
+    assert_eq!(grf::header(&spec, false, false)[..4], [4, 0, 0, 0]);
+}
+
+#[test]
+fn spec_round_trip() {
+    let original = spec();
+    let mut parsed = grf::parse_header(&grf::header(&original, true, false)).unwrap();
+    grf::parse_spec(&grf::spec(&original), &mut parsed).unwrap();
+
+    assert_eq!(
+        grf::header(&parsed, true, false),
+        grf::header(&original, true, false)
+    );
+    assert_eq!(grf::spec(&parsed), grf::spec(&original));
+    assert_eq!(parsed.map_layout, MapLayout::ScreenBlock);
+    assert_eq!(parsed.map_offset, 3);
+    assert!(matches!(
+        parsed.transparency,
+        Transparency::Color(Color::Bgr555(Bgr555(0x7C1F)))
+    ));
+}
+
+#[test]
+fn custom_entry_round_trip() {
+    let original = BitmapSpec {
+        map_entry_format: MapEntryFormat {
+            bits: 32,
+            index: MapField::new(0, 16),
+            hflip: MapField::new(16, 1),
+            vflip: MapField::new(17, 1),
+            palette: MapField::new(20, 4),
+        },
+        ..spec()
+    };
+    let mut parsed = grf::parse_header(&grf::header(&original, true, false)).unwrap();
+    grf::parse_spec(&grf::spec(&original), &mut parsed).unwrap();
+
+    assert_eq!(parsed.map_entry_format, original.map_entry_format);
+}
+
+#[test]
+fn parse_round_trip() {
+    let spec = spec();
+    let (gfx, palette, map) = ([0x11; 64], [0x22; 32], [0x33; 8]);
+    let file = riff(&[
+        (b"HDR ", &grf::header(&spec, true, false)),
+        (b"RGRT", &grf::spec(&spec)),
+        (b"GFX ", &section(&gfx)),
+        (b"PAL ", &section(&palette)),
+        (b"MAP ", &section(&map)),
+    ]);
+
+    let grf = Grf::parse(&file).unwrap();
+    assert_eq!(grf.gfx, gfx);
+    assert_eq!(grf.palette, palette);
+    assert_eq!(grf.map, map);
+    assert!(grf.meta.is_empty());
+    assert_eq!(grf.spec.tile_count, 2);
+    assert_eq!(
+        grf.spec.palette_range,
+        Some(PaletteRange { start: 16, end: 32 })
+    );
+}
+
+#[test]
+fn parse_metatiles() {
+    let spec = BitmapSpec {
+        meta_width: 2,
+        meta_height: 2,
+        ..spec()
+    };
+    let (map, meta) = ([0x44; 4], [0x55; 8]);
+    let file = riff(&[
+        (b"HDR ", &grf::header(&spec, true, true)),
+        (b"MMAP", &section(&map)),
+        (b"MTIL", &section(&meta)),
+    ]);
+
+    let grf = Grf::parse(&file).unwrap();
+    assert_eq!(grf.map, map);
+    assert_eq!(grf.meta, meta);
+    assert_eq!(grf.spec.map_count, 2);
+}
+
+#[test]
+fn parse_grit_file() {
+    // A 16x16 4bpp tileset without map, laid out the way grit writes it
+    let header = [4, 0, 0, 16, 8, 8, 1, 1, 16, 0, 0, 0, 16, 0, 0, 0];
+    let (gfx, palette) = ([0x12; 128], [0x34; 32]);
+    let file = riff(&[
+        (b"HDR ", &header),
+        (b"GFX ", &section(&gfx)),
+        (b"PAL ", &section(&palette)),
+    ]);
+
+    let grf = Grf::parse(&file).unwrap();
+    assert_eq!(grf.gfx, gfx);
+    assert_eq!(grf.palette, palette);
+    assert!(grf.map.is_empty());
+    assert!(matches!(grf.spec.format, GfxFormat::Tile));
+    assert_eq!(grf.spec.bpp, 4);
+    assert_eq!((grf.spec.width, grf.spec.height), (16, 16));
+    assert_eq!(grf.spec.tile_count, 4);
+    assert_eq!(grf.spec.palette_count, 16);
+    assert_eq!(grf.spec.gfx_compression, Compression::Off);
+    assert!(!grf.spec.sections.map);
+}
+
+#[test]
+fn parse_grit_compressed() {
+    // Compressed sections keep their header, the size in it is the uncompressed one
+    let header = [8, 16, 0, 0, 8, 8, 1, 1, 8, 0, 0, 0, 8, 0, 0, 0];
+    let gfx = [0x10, 64, 0, 0, 0xAA, 0xBB];
+    let map = section(&[0, 0]);
+    let file = riff(&[(b"HDR ", &header), (b"GFX ", &gfx), (b"MAP ", &map)]);
+
+    let grf = Grf::parse(&file).unwrap();
+    assert_eq!(grf.gfx, gfx);
+    assert_eq!(grf.map, [0, 0]);
+    assert_eq!(grf.spec.gfx_compression, Compression::LZ77);
+    assert_eq!(grf.spec.map_compression, Compression::Off);
+    assert_eq!(grf.spec.tile_count, 1);
+    assert_eq!(grf.spec.map_count, 1);
+    assert_eq!(grf.spec.palette_count, 0);
+}
+
+#[test]
+fn parse_errors() {
+    assert_eq!(Grf::parse(b"RIFX").unwrap_err(), GrfError::NotGrf);
+    assert_eq!(
+        Grf::parse(&riff(&[(b"GFX ", &[])])).unwrap_err(),
+        GrfError::MissingHeader
+    );
+    assert_eq!(
+        Grf::parse(&riff(&[(b"HDR ", &[4, 0, 0])])).unwrap_err(),
+        GrfError::Truncated
+    );
+
+    let mut header = grf::header(&spec(), false, false);
+    header[0] = 3;
+    assert_eq!(
+        Grf::parse(&riff(&[(b"HDR ", &header)])).unwrap_err(),
+        GrfError::BadHeader("bit depth")
+    );
+
+    let mut file = riff(&[(b"HDR ", &grf::header(&spec(), false, false))]);
+    file.truncate(file.len() - 4);
+    assert_eq!(Grf::parse(&file).unwrap_err(), GrfError::Truncated);
+}
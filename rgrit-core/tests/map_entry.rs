@@ -0,0 +1,79 @@
+use rgrit_core::{MapEntryFormat, MapEntryFormatError, MapField};
+
+#[test]
+fn parse_screen_entry() {
+    assert_eq!(
+        "16:i10h1v1p4".parse::<MapEntryFormat>(),
+        Ok(MapEntryFormat::SCREEN_ENTRY)
+    );
+    assert_eq!("8:i8".parse::<MapEntryFormat>(), Ok(MapEntryFormat::AFFINE));
+}
+
+#[test]
+fn parse_unused_bits() {
+    let format = "32:i12_4p4_2h1v1".parse::<MapEntryFormat>().unwrap();
+    assert_eq!(format.bits, 32);
+    assert_eq!(format.index, MapField::new(0, 12));
+    assert_eq!(format.palette, MapField::new(16, 4));
+    assert_eq!(format.hflip, MapField::new(22, 1));
+    assert_eq!(format.vflip, MapField::new(23, 1));
+}
+
+#[test]
+fn parse_errors() {
+    for (text, error) in [
+        ("16", MapEntryFormatError::Syntax),
+        ("x:i10", MapEntryFormatError::Syntax),
+        ("16:i", MapEntryFormatError::Syntax),
+        ("16:i10x2", MapEntryFormatError::UnknownField('x')),
+        ("12:i10", MapEntryFormatError::BadSize(12)),
+        ("16:i10p8", MapEntryFormatError::TooLong),
+        ("16:i8i2", MapEntryFormatError::Overlap),
+        ("16:h1v1", MapEntryFormatError::NoIndex),
+        ("32:_200i8", MapEntryFormatError::TooLong),
+    ] {
+        assert_eq!(text.parse::<MapEntryFormat>(), Err(error), "{text}");
+    }
+}
+
+#[test]
+fn field_get() {
+    let format = MapEntryFormat::SCREEN_ENTRY;
+    let entry = 0x5C2A;
+    assert_eq!(format.index.get(entry), 0x02A);
+    assert_eq!(format.hflip.get(entry), 1);
+    assert_eq!(format.vflip.get(entry), 1);
+    assert_eq!(format.palette.get(entry), 5);
+}
+
+#[test]
+fn field_round_trip() {
+    let format = "32:i16h1v1_2p4".parse::<MapEntryFormat>().unwrap();
+    let fields = [
+        (format.index, 0xBEEF),
+        (format.hflip, 1),
+        (format.vflip, 0),
+        (format.palette, 0xA),
+    ];
+    let entry = fields.iter().fold(0, |entry, (field, value)| {
+        entry | (value << field.shift) & field.mask()
+    });
+
+    assert_eq!(entry, 0x00A1_BEEF);
+    for (field, value) in fields {
+        assert_eq!(field.get(entry), value);
+    }
+}
+
+#[test]
+fn field_mask() {
+    assert_eq!(MapField::NONE.mask(), 0);
+    assert_eq!(MapField::new(4, 4).mask(), 0xF0);
+    assert_eq!(MapField::new(0, 32).mask(), u32::MAX);
+
+    // Out of range fields don't panic, their bits past the entry are left out
+    assert_eq!(MapField::new(0, 40).mask(), u32::MAX);
+    assert_eq!(MapField::new(28, 8).mask(), 0xF000_0000);
+    assert_eq!(MapField::new(32, 1).mask(), 0);
+    assert_eq!(MapField::new(200, 1).get(u32::MAX), 0);
+}
@@ -0,0 +1,71 @@
+//! Content addressed storage for converted data so the macro can expand to `include_bytes!`.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use rgrit_rs::codegen;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+/// The cache lives in `OUT_DIR` when the calling crate has a build script and in the system's
+/// temporary directory otherwise.
+fn cache_dir() -> PathBuf {
+    std::env::var_os("OUT_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("rgrit")
+}
+
+/// Stores `data` in the cache and returns its path.
+///
+/// Files are named after a hash of their contents, so concurrent expansions never write
+/// different data to the same file.
+fn store(data: &[u8]) -> std::io::Result<PathBuf> {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+
+    let dir = cache_dir();
+    let path = dir.join(format!("{:016x}.bin", hasher.finish()));
+
+    if std::fs::metadata(&path).ok().map(|m| m.len()) != Some(data.len() as u64) {
+        std::fs::create_dir_all(&dir)?;
+        // Write to a unique file first so readers never see a partially written file
+        let tmp = dir.join(format!(
+            "{:016x}.{}.tmp",
+            hasher.finish(),
+            std::process::id()
+        ));
+        std::fs::write(&tmp, data)?;
+        std::fs::rename(&tmp, &path)?;
+    }
+
+    Ok(path)
+}
+
+/// Returns a [`codegen::aligned`] expression that includes `data` from the cache. Falls back to
+/// a byte array literal if the cache can't be written.
+pub fn bytes(data: &[u8]) -> TokenStream {
+    if data.is_empty() {
+        return quote! { &[] };
+    }
+
+    let value = match store(data) {
+        Ok(path) => {
+            let path = path.to_string_lossy();
+            quote! { *include_bytes!(#path) }
+        }
+        Err(_) => return codegen::bytes(data),
+    };
+
+    codegen::aligned(data.len(), value)
+}
+
+/// Returns an item that makes cargo rebuild the calling crate when `input` changes.
+pub fn track(input: &str) -> TokenStream {
+    match Path::new(input).canonicalize() {
+        Ok(path) => {
+            let path = path.to_string_lossy();
+            quote! { const _: &[u8] = include_bytes!(#path); }
+        }
+        Err(_) => quote! {},
+    }
+}
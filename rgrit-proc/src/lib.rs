@@ -1,21 +1,447 @@
+mod cache;
+
+use rgrit_core::Bgr555;
+use rgrit_core::Color;
 use rgrit_core::Compression;
+use rgrit_core::DataType;
 use rgrit_core::GfxFormat;
+use rgrit_core::MapLayout;
+use rgrit_core::MapReduction;
+use rgrit_core::ObjMapping;
+use rgrit_core::Transparency;
+use rgrit_rs::codegen;
 use rgrit_rs::Bitmap;
 use rgrit_rs::BitmapBuilder;
+use rgrit_rs::Setting;
+use rgrit_rs::{Batch, BatchBuilder};
+use rgrit_rs::{SpriteSheet, SpriteSheetBuilder};
 
 use proc_macro::TokenStream;
+use proc_macro2::Span;
+use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
 use syn::Ident;
 use syn::LitInt;
-use syn::{parse::Parse, parse_macro_input, LitStr};
+use syn::{parse::Parse, LitStr};
 
 #[derive(Debug, Clone)]
-struct Grit {
-    bitmap: Bitmap,
+enum Grit {
+    Single { input: String, bitmap: Bitmap },
+    Batch { inputs: Vec<String>, batch: Batch },
+    Sheet { input: String, sheet: SpriteSheet },
+}
+
+fn parse_compression(input: syn::parse::ParseStream) -> syn::Result<Compression> {
+    let compression_ident = input.parse::<Ident>()?;
+
+    match compression_ident.to_string().as_str() {
+        "Off" => Ok(Compression::Off),
+        "LZ77" => Ok(Compression::LZ77),
+        "Huffman" => Ok(Compression::Huffman),
+        "RLE" => Ok(Compression::RLE),
+        "OffHeader" => Ok(Compression::OffHeader),
+        _ => Err(syn::Error::new(
+            compression_ident.span(),
+            "Unknown compression",
+        )),
+    }
+}
+
+fn parse_data_type(input: syn::parse::ParseStream) -> syn::Result<DataType> {
+    let data_type_ident = input.parse::<Ident>()?;
+
+    match data_type_ident.to_string().as_str() {
+        "u8" => Ok(DataType::U8),
+        "u16" => Ok(DataType::U16),
+        "u32" => Ok(DataType::U32),
+        _ => Err(syn::Error::new(
+            data_type_ident.span(),
+            "Unknown data type, expected u8, u16 or u32",
+        )),
+    }
+}
+
+/// Parses `Disabled`, `FirstPaletteEntry`, `TopLeftPixel`, `rgb(r, g, b)`, `bgr555(0x7C1F)` or a
+/// hex color. Like grit's `-gT`, six hex digits are RGB and up to four are a raw BGR555 value.
+fn parse_transparency(input: syn::parse::ParseStream) -> syn::Result<Transparency> {
+    if input.peek(LitInt) {
+        let lit = input.parse::<LitInt>()?;
+        let text = lit.to_string();
+        let digits = text
+            .strip_suffix(lit.suffix())
+            .and_then(|text| text.strip_prefix("0x"))
+            .map(|hex| hex.replace('_', ""));
+        let value = lit.base10_parse::<u32>()?;
+
+        return match digits.map(|digits| digits.len()) {
+            Some(5..=6) => Ok(Transparency::Color(Color::RGB {
+                r: (value >> 16) as u8,
+                g: (value >> 8) as u8,
+                b: value as u8,
+            })),
+            Some(1..=4) => Ok(Transparency::Color(Color::Bgr555(Bgr555(value as u16)))),
+            _ => Err(syn::Error::new(
+                lit.span(),
+                "Expected a hex color like 0xFF00FF or a BGR555 value like 0x7C1F",
+            )),
+        };
+    }
+
+    let ident = input.parse::<Ident>()?;
+    match ident.to_string().as_str() {
+        "Disabled" => Ok(Transparency::Disabled),
+        "FirstPaletteEntry" => Ok(Transparency::FirstPaletteEntry),
+        "TopLeftPixel" => Ok(Transparency::TopLeftPixel),
+        "rgb" => {
+            let content;
+            syn::parenthesized!(content in input);
+            let channels = content.parse_terminated(LitInt::parse, syn::Token![,])?;
+            let [r, g, b] = channels
+                .iter()
+                .map(LitInt::base10_parse::<u8>)
+                .collect::<syn::Result<Vec<_>>>()?[..]
+            else {
+                return Err(syn::Error::new(ident.span(), "Expected rgb(r, g, b)"));
+            };
+            Ok(Transparency::Color(Color::RGB { r, g, b }))
+        }
+        "bgr555" => {
+            let content;
+            syn::parenthesized!(content in input);
+            let lit = content.parse::<LitInt>()?;
+            Ok(Transparency::Color(Color::Bgr555(Bgr555(
+                lit.base10_parse()?,
+            ))))
+        }
+        _ => Err(syn::Error::new(ident.span(), "Unknown transparency")),
+    }
+}
+
+/// The keys given to `grit!`, so errors can point at the setting they are about.
+#[derive(Default)]
+struct Keys(Vec<Ident>);
+
+impl Keys {
+    fn span(&self, setting: Setting) -> Option<Span> {
+        // The last occurrence of a key is the one that took effect
+        self.0
+            .iter()
+            .rev()
+            .find(|key| *key == setting.name())
+            .map(Ident::span)
+    }
+}
+
+/// Turns a failed conversion into a compile error at `span`.
+///
+/// Validation errors point at the offending key instead. Every message grit logged becomes its
+/// own error, so the real cause shows up at the call site.
+fn conversion_error(span: Span, context: String, e: rgrit_rs::Error, keys: &Keys) -> syn::Error {
+    let settings = e.settings();
+    let mut spans = settings.iter().filter_map(|setting| keys.span(*setting));
+
+    match e {
+        rgrit_rs::Error::Grit { messages, .. } => {
+            let mut errors = messages
+                .iter()
+                .map(|message| syn::Error::new(span, format!("{context}: {message}")));
+            let mut error = errors
+                .next()
+                .expect("grit errors carry at least one message");
+            errors.for_each(|e| error.combine(e));
+            error
+        }
+        rgrit_rs::Error::Conflict { .. } => {
+            let mut error = syn::Error::new(spans.next().unwrap_or(span), e.to_string());
+            if let (Some(other), [_, setting]) = (spans.next(), settings.as_slice()) {
+                error.combine(syn::Error::new(other, format!("{setting} is set here")));
+            }
+            error
+        }
+        e => match spans.next() {
+            Some(span) => syn::Error::new(span, e.to_string()),
+            None => syn::Error::new(span, format!("{context}: {e}")),
+        },
+    }
+}
+
+/// Parses the `key = value` settings that follow the input(s) into `builder`.
+fn parse_settings(
+    input: syn::parse::ParseStream,
+    mut builder: BitmapBuilder,
+    sheet: &mut Option<SpriteSheetBuilder>,
+    keys: &mut Keys,
+) -> syn::Result<BitmapBuilder> {
+    while !input.is_empty() {
+        let ident = input.parse::<Ident>()?;
+        input.parse::<syn::Token![=]>()?;
+        keys.0.push(ident.clone());
+
+        match ident.to_string().as_str() {
+            "transparency" => {
+                builder = builder.with_transparency(parse_transparency(input)?);
+            }
+            "bit_depth" => {
+                if input.peek(Ident) {
+                    let ident = input.parse::<Ident>()?;
+                    match ident.to_string().as_str() {
+                        "A3I5" => {
+                            builder = builder.with_bit_depth_override(rgrit_core::BitDepth::A3I5)
+                        }
+                        "A5I3" => {
+                            builder = builder.with_bit_depth_override(rgrit_core::BitDepth::A5I3)
+                        }
+                        "FourByFour" | "4x4" => {
+                            builder =
+                                builder.with_bit_depth_override(rgrit_core::BitDepth::FourByFour)
+                        }
+                        _ => return Err(syn::Error::new(ident.span(), "Unknown bit depth")),
+                    }
+                } else if input.peek(LitInt) {
+                    let lit = input.parse::<LitInt>()?;
+                    builder = builder
+                        .with_bit_depth_override(rgrit_core::BitDepth::Custom(lit.base10_parse()?));
+                } else {
+                    return Err(syn::Error::new(
+                        input.span(),
+                        "Expected identifier or literal",
+                    ));
+                }
+            }
+            "texture" => {
+                builder = builder.with_texture(input.parse::<syn::LitBool>()?.value);
+            }
+            "format" => {
+                let format_ident = input.parse::<Ident>()?;
+
+                match format_ident.to_string().as_str() {
+                    "Bitmap" => builder = builder.with_format(GfxFormat::Bitmap),
+                    "Tile" => builder = builder.with_format(GfxFormat::Tile),
+                    _ => return Err(syn::Error::new(format_ident.span(), "Unknown format")),
+                }
+            }
+            "tile_width" => {
+                let lit = input.parse::<LitInt>()?;
+                builder = builder.with_tile_width(lit.base10_parse()?);
+            }
+            "tile_height" => {
+                let lit = input.parse::<LitInt>()?;
+                builder = builder.with_tile_height(lit.base10_parse()?);
+            }
+            "meta_width" => {
+                let lit = input.parse::<LitInt>()?;
+                builder = builder.with_meta_width(lit.base10_parse()?);
+            }
+            "meta_height" => {
+                let lit = input.parse::<LitInt>()?;
+                builder = builder.with_meta_height(lit.base10_parse()?);
+            }
+            "map_reduction" => {
+                let mut map_reduction = MapReduction::NONE;
+
+                loop {
+                    let flag = input.parse::<Ident>()?;
+                    match flag.to_string().as_str() {
+                        "None" => {}
+                        "Tiles" => map_reduction.tiles = true,
+                        "Flips" => map_reduction.flips = true,
+                        "Palettes" => map_reduction.palettes = true,
+                        _ => return Err(syn::Error::new(flag.span(), "Unknown map reduction")),
+                    }
+
+                    if !input.peek(syn::Token![|]) {
+                        break;
+                    }
+                    input.parse::<syn::Token![|]>()?;
+                }
+
+                builder = builder.with_map_reduction(map_reduction);
+            }
+            "map_layout" => {
+                let layout_ident = input.parse::<Ident>()?;
+
+                match layout_ident.to_string().as_str() {
+                    "Flat" => builder = builder.with_map_layout(MapLayout::Flat),
+                    "ScreenBlock" => builder = builder.with_map_layout(MapLayout::ScreenBlock),
+                    "Affine" => builder = builder.with_map_layout(MapLayout::Affine),
+                    _ => return Err(syn::Error::new(layout_ident.span(), "Unknown map layout")),
+                }
+            }
+            "palette_start" => {
+                let lit = input.parse::<LitInt>()?;
+                builder = builder.with_palette_start(lit.base10_parse()?);
+            }
+            "palette_end" => {
+                let lit = input.parse::<LitInt>()?;
+                builder = builder.with_palette_end(lit.base10_parse()?);
+            }
+            "palette_count" => {
+                let lit = input.parse::<LitInt>()?;
+                builder = builder.with_palette_count(lit.base10_parse()?);
+            }
+            "area_left" => {
+                let lit = input.parse::<LitInt>()?;
+                builder = builder.with_area_left(lit.base10_parse()?);
+            }
+            "area_right" => {
+                let lit = input.parse::<LitInt>()?;
+                builder = builder.with_area_right(lit.base10_parse()?);
+            }
+            "area_width" => {
+                let lit = input.parse::<LitInt>()?;
+                builder = builder.with_area_width(lit.base10_parse()?);
+            }
+            "area_top" => {
+                let lit = input.parse::<LitInt>()?;
+                builder = builder.with_area_top(lit.base10_parse()?);
+            }
+            "area_bottom" => {
+                let lit = input.parse::<LitInt>()?;
+                builder = builder.with_area_bottom(lit.base10_parse()?);
+            }
+            "area_height" => {
+                let lit = input.parse::<LitInt>()?;
+                builder = builder.with_area_height(lit.base10_parse()?);
+            }
+            "map_entry_format" => {
+                let lit = input.parse::<LitStr>()?;
+                let format = lit
+                    .value()
+                    .parse()
+                    .map_err(|e| syn::Error::new(lit.span(), format!("{e}")))?;
+                builder = builder.with_map_entry_format(format);
+            }
+            "gfx" => {
+                builder = builder.with_gfx(input.parse::<syn::LitBool>()?.value);
+            }
+            "palette" => {
+                builder = builder.with_palette(input.parse::<syn::LitBool>()?.value);
+            }
+            "map" => {
+                builder = builder.with_map(input.parse::<syn::LitBool>()?.value);
+            }
+            "gfx_offset" => {
+                let lit = input.parse::<LitInt>()?;
+                builder = builder.with_gfx_offset(lit.base10_parse()?);
+            }
+            "map_offset" => {
+                let lit = input.parse::<LitInt>()?;
+                builder = builder.with_map_offset(lit.base10_parse()?);
+            }
+            "compression" => {
+                builder = builder.with_compression(parse_compression(input)?);
+            }
+            "gfx_compression" => {
+                builder = builder.with_gfx_compression(parse_compression(input)?);
+            }
+            "palette_compression" => {
+                builder = builder.with_palette_compression(parse_compression(input)?);
+            }
+            "map_compression" => {
+                builder = builder.with_map_compression(parse_compression(input)?);
+            }
+            "data_type" => {
+                builder = builder.with_data_type(parse_data_type(input)?);
+            }
+            "gfx_data_type" => {
+                builder = builder.with_gfx_data_type(parse_data_type(input)?);
+            }
+            "palette_data_type" => {
+                builder = builder.with_palette_data_type(parse_data_type(input)?);
+            }
+            "map_data_type" => {
+                builder = builder.with_map_data_type(parse_data_type(input)?);
+            }
+            "frame_width" => {
+                let lit = input.parse::<LitInt>()?;
+                *sheet = Some(
+                    sheet
+                        .unwrap_or_default()
+                        .with_frame_width(lit.base10_parse()?),
+                );
+            }
+            "frame_height" => {
+                let lit = input.parse::<LitInt>()?;
+                *sheet = Some(
+                    sheet
+                        .unwrap_or_default()
+                        .with_frame_height(lit.base10_parse()?),
+                );
+            }
+            "frame_count" => {
+                let lit = input.parse::<LitInt>()?;
+                *sheet = Some(
+                    sheet
+                        .unwrap_or_default()
+                        .with_frame_count(lit.base10_parse()?),
+                );
+            }
+            "obj_mapping" => {
+                let ident = input.parse::<Ident>()?;
+                let mapping = match ident.to_string().as_str() {
+                    "OneD" => ObjMapping::OneD,
+                    "TwoD" => ObjMapping::TwoD,
+                    _ => return Err(syn::Error::new(ident.span(), "Expected OneD or TwoD")),
+                };
+                *sheet = Some(sheet.unwrap_or_default().with_mapping(mapping));
+            }
+            _ => return Err(syn::Error::new(ident.span(), "Unknown attribute")),
+        };
+
+        if input.peek(syn::Token![,]) {
+            input.parse::<syn::Token![,]>()?;
+        }
+    }
+
+    Ok(builder)
 }
 
 impl Parse for Grit {
     fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        if input.peek(syn::token::Bracket) {
+            let content;
+            let bracket = syn::bracketed!(content in input);
+            let inputs = content.parse_terminated(|i| i.parse::<LitStr>(), syn::Token![,])?;
+
+            let mut settings = BitmapBuilder::default();
+            let mut sheet = None;
+            let mut keys = Keys::default();
+            if !input.is_empty() {
+                input.parse::<syn::Token![,]>()?;
+                settings = parse_settings(input, settings, &mut sheet, &mut keys)?;
+            }
+            if sheet.is_some() {
+                let span = [
+                    Setting::FrameWidth,
+                    Setting::FrameHeight,
+                    Setting::FrameCount,
+                    Setting::ObjMapping,
+                ]
+                .into_iter()
+                .find_map(|setting| keys.span(setting))
+                .unwrap_or(bracket.span.join());
+                return Err(syn::Error::new(
+                    span,
+                    "Sprite sheets are sliced from a single input",
+                ));
+            }
+
+            let inputs = inputs.iter().map(LitStr::value).collect::<Vec<_>>();
+            let batch = BatchBuilder::new(&inputs)
+                .with_settings(settings)
+                .build()
+                .map_err(|e| {
+                    conversion_error(
+                        bracket.span.join(),
+                        "Failed to convert batch".into(),
+                        e,
+                        &keys,
+                    )
+                })?;
+
+            return Ok(Grit::Batch { inputs, batch });
+        }
+
         let lit = input.parse::<LitStr>()?;
 
         if input.is_empty() {
@@ -25,213 +451,115 @@ impl Parse for Grit {
                 .with_format(GfxFormat::Bitmap)
                 .build()
                 .map_err(|e| {
-                    let msg = format!("Failed to load {}: {}", lit.value(), e);
-                    syn::Error::new(lit.span(), msg)
+                    let context = format!("Failed to load {}", lit.value());
+                    conversion_error(lit.span(), context, e, &Keys::default())
                 })?;
 
-            Ok(Grit { bitmap })
+            Ok(Grit::Single {
+                input: lit.value(),
+                bitmap,
+            })
         } else {
             if !input.peek(syn::Token![,]) {
                 return Err(syn::Error::new(input.span(), "Expected comma after input"));
             }
             input.parse::<syn::Token![,]>()?;
 
-            let mut builder = BitmapBuilder::new(lit.value());
-
-            while !input.is_empty() {
-                let ident = input.parse::<Ident>()?;
-                input.parse::<syn::Token![=]>()?;
-
-                match ident.to_string().as_str() {
-                    "transparency" => {
-                        if input.peek(Ident) {
-                            let ident = input.parse::<Ident>()?;
-                            match ident.to_string().as_str() {
-                                "Disabled" => {
-                                    builder = builder
-                                        .with_transparency(rgrit_core::Transparency::Disabled)
-                                }
-                                _ => {
-                                    return Err(syn::Error::new(
-                                        ident.span(),
-                                        "Unknown transparency",
-                                    ))
-                                }
-                            }
-                        } else if input.peek(LitInt) {
-                            todo!()
-                        } else {
-                            return Err(syn::Error::new(
-                                input.span(),
-                                "Expected identifier or literal",
-                            ));
-                        }
-                    }
-                    "bit_depth" => {
-                        if input.peek(Ident) {
-                            let ident = input.parse::<Ident>()?;
-                            match ident.to_string().as_str() {
-                                "A3I5" => {
-                                    builder =
-                                        builder.with_bit_depth_override(rgrit_core::BitDepth::A3I5)
-                                }
-                                "A5I3" => {
-                                    builder =
-                                        builder.with_bit_depth_override(rgrit_core::BitDepth::A5I3)
-                                }
-                                "FourByFour" | "4x4" => {
-                                    builder = builder
-                                        .with_bit_depth_override(rgrit_core::BitDepth::FourByFour)
-                                }
-                                _ => {
-                                    return Err(syn::Error::new(ident.span(), "Unknown bit depth"))
-                                }
-                            }
-                        } else if input.peek(LitInt) {
-                            let lit = input.parse::<LitInt>()?;
-                            builder = builder.with_bit_depth_override(
-                                rgrit_core::BitDepth::Custom(lit.base10_parse()?),
-                            );
-                        } else {
-                            return Err(syn::Error::new(
-                                input.span(),
-                                "Expected identifier or literal",
-                            ));
-                        }
-                    }
-                    "format" => {
-                        let format_ident = input.parse::<Ident>()?;
-
-                        match format_ident.to_string().as_str() {
-                            "Bitmap" => builder = builder.with_format(GfxFormat::Bitmap),
-                            "Tile" => builder = builder.with_format(GfxFormat::Tile),
-                            _ => {
-                                return Err(syn::Error::new(format_ident.span(), "Unknown format"))
-                            }
-                        }
-                    }
-                    "tile_width" => {
-                        let lit = input.parse::<LitInt>()?;
-                        builder = builder.with_tile_width(lit.base10_parse()?);
-                    }
-                    "tile_height" => {
-                        let lit = input.parse::<LitInt>()?;
-                        builder = builder.with_tile_height(lit.base10_parse()?);
-                    }
-                    "meta_width" => {
-                        let lit = input.parse::<LitInt>()?;
-                        builder = builder.with_meta_width(lit.base10_parse()?);
-                    }
-                    "meta_height" => {
-                        let lit = input.parse::<LitInt>()?;
-                        builder = builder.with_meta_height(lit.base10_parse()?);
-                    }
-                    "area_left" => {
-                        let lit = input.parse::<LitInt>()?;
-                        builder = builder.with_area_left(lit.base10_parse()?);
-                    }
-                    "area_right" => {
-                        let lit = input.parse::<LitInt>()?;
-                        builder = builder.with_area_right(lit.base10_parse()?);
-                    }
-                    "area_width" => {
-                        let lit = input.parse::<LitInt>()?;
-                        builder = builder.with_area_width(lit.base10_parse()?);
-                    }
-                    "area_top" => {
-                        let lit = input.parse::<LitInt>()?;
-                        builder = builder.with_area_top(lit.base10_parse()?);
-                    }
-                    "area_bottom" => {
-                        let lit = input.parse::<LitInt>()?;
-                        builder = builder.with_area_bottom(lit.base10_parse()?);
-                    }
-                    "area_height" => {
-                        let lit = input.parse::<LitInt>()?;
-                        builder = builder.with_area_height(lit.base10_parse()?);
-                    }
-                    "compression" => {
-                        let compression_ident = input.parse::<Ident>()?;
-
-                        match compression_ident.to_string().as_str() {
-                            "Off" => builder = builder.with_compression(Compression::Off),
-                            "LZ77" => builder = builder.with_compression(Compression::LZ77),
-                            "Huffman" => builder = builder.with_compression(Compression::Huffman),
-                            "RLE" => builder = builder.with_compression(Compression::RLE),
-                            "OffHeader" => {
-                                builder = builder.with_compression(Compression::OffHeader)
-                            }
-                            _ => {
-                                return Err(syn::Error::new(
-                                    compression_ident.span(),
-                                    "Unknown compression",
-                                ))
-                            }
-                        }
-                    }
-                    _ => return Err(syn::Error::new(ident.span(), "Unknown attribute")),
-                };
-
-                if input.peek(syn::Token![,]) {
-                    input.parse::<syn::Token![,]>()?;
-                }
-            }
+            let mut keys = Keys::default();
+            let mut sheet = None;
+            let builder = parse_settings(
+                input,
+                BitmapBuilder::new(lit.value()),
+                &mut sheet,
+                &mut keys,
+            )?;
 
             let bitmap = builder.build().map_err(|e| {
-                let msg = format!("Failed to load {}: {}", lit.value(), e);
-                syn::Error::new(lit.span(), msg)
+                let context = format!("Failed to load {}", lit.value());
+                conversion_error(lit.span(), context, e, &keys)
             })?;
 
-            Ok(Grit { bitmap })
+            if let Some(sheet) = sheet {
+                let sheet = sheet.build(&bitmap).map_err(|e| {
+                    let context = format!("Failed to slice {}", lit.value());
+                    conversion_error(lit.span(), context, e, &keys)
+                })?;
+
+                return Ok(Grit::Sheet {
+                    input: lit.value(),
+                    sheet,
+                });
+            }
+
+            Ok(Grit::Single {
+                input: lit.value(),
+                bitmap,
+            })
         }
     }
 }
 
 #[proc_macro]
 pub fn grit(input: TokenStream) -> TokenStream {
-    let input = parse_macro_input!(input as Grit);
-
-    // Put all the fields into a struct as `&'static [u8]`.
-    let gfx = input.bitmap.gfx;
-    let palette = input.bitmap.palette;
-    let map = input.bitmap.map;
-    let meta = input.bitmap.meta;
-
-    // Also put some metadata so we can automatically display it.
-    let bit_depth = match input.bitmap.spec.bit_depth {
-        Some(rgrit_core::BitDepth::A3I5) => quote! { Some(rgrit_core::BitDepth::A3I5) },
-        Some(rgrit_core::BitDepth::A5I3) => quote! { Some(rgrit_core::BitDepth::A5I3) },
-        Some(rgrit_core::BitDepth::FourByFour) => quote! { Some(rgrit_core::BitDepth::FourByFour) },
-        Some(rgrit_core::BitDepth::Custom(n)) => quote! { Some(rgrit_core::BitDepth::Custom(#n)) },
-
-        None => quote! { None },
-    };
-    let format = match input.bitmap.spec.format {
-        rgrit_core::GfxFormat::Bitmap => quote! { rgrit_core::GfxFormat::Bitmap },
-        rgrit_core::GfxFormat::Tile => quote! { rgrit_core::GfxFormat::Tile },
+    // The data is written to the cache and included from there, the inputs are included as
+    // well so cargo rebuilds the caller whenever an image changes.
+    let grit = match syn::parse::<Grit>(input) {
+        Ok(grit) => grit,
+        Err(e) => {
+            // Several errors expand to several `compile_error!`s, which only fit into a block
+            let errors = e.to_compile_error();
+            return quote! { { #errors unreachable!() } }.into();
+        }
     };
-    let transparency = match input.bitmap.spec.transparency {
-        rgrit_core::Transparency::Disabled => quote! { rgrit_core::Transparency::Disabled },
-        rgrit_core::Transparency::Color(rgrit_core::Color::RGB { r, g, b }) => {
-            quote! { grit_core::Transparency::Color(rgrit_core::Color::RGB { r: #r, g: #g, b: #b }) }
+
+    match grit {
+        Grit::Single { input, bitmap } => {
+            let track = cache::track(&input);
+            let bitmap = static_bitmap(&bitmap);
+
+            quote! {
+                {
+                    #track
+                    #bitmap
+                }
+            }
         }
-        rgrit_core::Transparency::Color(rgrit_core::Color::GBR16(clr)) => {
-            quote! { grit_core::Transparency::Color(rgrit_core::Color::GBR16(#clr)) }
+        Grit::Sheet { input, sheet } => {
+            let track = cache::track(&input);
+            let sheet = static_sprite_sheet(&sheet);
+
+            quote! {
+                {
+                    #track
+                    #sheet
+                }
+            }
         }
-    };
+        Grit::Batch { inputs, batch } => {
+            let track = inputs.iter().map(|input| cache::track(input));
+            let palette = cache::bytes(&batch.palette);
+            let bitmaps = batch.bitmaps.iter().map(static_bitmap).collect::<Vec<_>>();
+            let batch = codegen::static_batch_expr(palette, &bitmaps);
 
-    quote! {
-        rgrit::StaticBitmap {
-            gfx: &[#(#gfx),*],
-            palette: &[#(#palette),*],
-            map: &[#(#map),*],
-            meta: &[#(#meta),*],
-            spec: rgrit_core::BitmapSpec {
-                bit_depth: #bit_depth,
-                format: #format,
-                transparency: #transparency,
-            },
+            quote! {
+                {
+                    #(#track)*
+                    #batch
+                }
+            }
         }
     }
     .into()
 }
+
+fn static_bitmap(bitmap: &Bitmap) -> TokenStream2 {
+    // Put all the fields into a struct as `&'static [u8]`.
+    let sections =
+        [&bitmap.gfx, &bitmap.palette, &bitmap.map, &bitmap.meta].map(|s| cache::bytes(s));
+    codegen::static_bitmap_expr(sections, &bitmap.spec)
+}
+
+fn static_sprite_sheet(sheet: &SpriteSheet) -> TokenStream2 {
+    let sections = [&sheet.tiles, &sheet.palette].map(|s| cache::bytes(s));
+    codegen::static_sprite_sheet_expr(sections, sheet)
+}
@@ -0,0 +1,181 @@
+//! Asset conversion from `build.rs`.
+//!
+//! Converting in a build script runs grit once per change of the source image instead of on
+//! every expansion of the [`grit!`](https://docs.rs/rgrit/latest/rgrit/macro.grit.html) macro.
+//!
+//! ```no_run
+//! // build.rs
+//! use rgrit_core::GfxFormat;
+//! use rgrit_rs::build::Assets;
+//! use rgrit_rs::BitmapBuilder;
+//!
+//! fn main() {
+//!     Assets::new()
+//!         .with_bitmap(
+//!             "BACKGROUND",
+//!             BitmapBuilder::new("assets/bg.png").with_format(GfxFormat::Tile),
+//!         )
+//!         .write("assets.rs")
+//!         .unwrap();
+//! }
+//! ```
+//!
+//! The generated module is then included with
+//! `include!(concat!(env!("OUT_DIR"), "/assets.rs"));`.
+
+use crate::{
+    codegen, BatchBuilder, Bitmap, BitmapBuilder, Error, Result, Source, SpriteSheetBuilder,
+};
+use proc_macro2::TokenStream;
+use quote::quote;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug)]
+enum Asset {
+    Bitmap(BitmapBuilder),
+    Batch(BatchBuilder),
+    SpriteSheet(BitmapBuilder, SpriteSheetBuilder),
+}
+
+/// A list of assets that are converted into `OUT_DIR`.
+#[derive(Clone, Debug, Default)]
+pub struct Assets {
+    assets: Vec<(String, Asset)>,
+}
+
+impl Assets {
+    pub fn new() -> Assets {
+        Assets::default()
+    }
+
+    /// Adds a `pub static {name}: rgrit::StaticBitmap` to the generated module.
+    pub fn with_bitmap(mut self, name: impl AsRef<str>, builder: BitmapBuilder) -> Self {
+        self.assets
+            .push((name.as_ref().to_string(), Asset::Bitmap(builder)));
+        self
+    }
+
+    /// Adds a `pub static {name}: rgrit::StaticBatch` to the generated module.
+    pub fn with_batch(mut self, name: impl AsRef<str>, builder: BatchBuilder) -> Self {
+        self.assets
+            .push((name.as_ref().to_string(), Asset::Batch(builder)));
+        self
+    }
+
+    /// Adds a `pub static {name}: rgrit::StaticSpriteSheet` to the generated module, converted
+    /// with `builder` and sliced by `sheet`.
+    pub fn with_sprite_sheet(
+        mut self,
+        name: impl AsRef<str>,
+        builder: BitmapBuilder,
+        sheet: SpriteSheetBuilder,
+    ) -> Self {
+        self.assets.push((
+            name.as_ref().to_string(),
+            Asset::SpriteSheet(builder, sheet),
+        ));
+        self
+    }
+
+    /// Converts all assets and writes the module to `$OUT_DIR/{module}`.
+    ///
+    /// Returns the path of the generated module.
+    pub fn write(&self, module: impl AsRef<Path>) -> Result<PathBuf> {
+        let out_dir = std::env::var_os("OUT_DIR")
+            .map(PathBuf::from)
+            .ok_or_else(|| Error::Io("OUT_DIR is not set, call this from build.rs".into()))?;
+
+        let mut items = TokenStream::new();
+
+        for (name, asset) in &self.assets {
+            let (ty, expr) = match asset {
+                Asset::Bitmap(builder) => {
+                    rerun_if_changed(&builder.source);
+                    let bitmap = builder.build()?;
+                    let expr = write_bitmap(&out_dir, &name.to_lowercase(), &bitmap)?;
+                    (quote! { ::rgrit::StaticBitmap }, expr)
+                }
+                Asset::Batch(builder) => {
+                    for input in &builder.inputs {
+                        println!("cargo:rerun-if-changed={input}");
+                    }
+                    let batch = builder.build()?;
+                    let file = name.to_lowercase();
+
+                    let palette =
+                        write_section(&out_dir, &format!("{file}.pal.bin"), &batch.palette)?;
+                    let bitmaps = batch
+                        .bitmaps
+                        .iter()
+                        .enumerate()
+                        .map(|(i, bitmap)| write_bitmap(&out_dir, &format!("{file}_{i}"), bitmap))
+                        .collect::<Result<Vec<_>>>()?;
+
+                    let expr = codegen::static_batch_expr(palette, &bitmaps);
+                    (quote! { ::rgrit::StaticBatch }, expr)
+                }
+                Asset::SpriteSheet(builder, sheet) => {
+                    rerun_if_changed(&builder.source);
+                    let sheet = sheet.build(&builder.build()?)?;
+                    let file = name.to_lowercase();
+
+                    let tiles = write_section(&out_dir, &format!("{file}.img.bin"), &sheet.tiles)?;
+                    let palette =
+                        write_section(&out_dir, &format!("{file}.pal.bin"), &sheet.palette)?;
+                    let expr = codegen::static_sprite_sheet_expr([tiles, palette], &sheet);
+                    (quote! { ::rgrit::StaticSpriteSheet }, expr)
+                }
+            };
+            items.extend(codegen::item(name, ty, expr));
+        }
+
+        let source = format!(
+            "// Generated by rgrit, do not edit.\n\n{}",
+            codegen::source(items)
+        );
+        let path = out_dir.join(module);
+        std::fs::write(&path, source).map_err(|e| Error::Io(format!("{}: {e}", path.display())))?;
+
+        Ok(path)
+    }
+}
+
+fn rerun_if_changed(source: &Source) {
+    if let Source::Path(path) = source {
+        println!("cargo:rerun-if-changed={path}");
+    }
+}
+
+/// Writes the sections of `bitmap` as `{file}.{img,pal,map,meta}.bin` and returns the
+/// `::rgrit::StaticBitmap` expression that includes them.
+fn write_bitmap(out_dir: &Path, file: &str, bitmap: &Bitmap) -> Result<TokenStream> {
+    let sections = [
+        ("img", &bitmap.gfx),
+        ("pal", &bitmap.palette),
+        ("map", &bitmap.map),
+        ("meta", &bitmap.meta),
+    ]
+    .map(|(extension, data)| write_section(out_dir, &format!("{file}.{extension}.bin"), data));
+
+    let [gfx, palette, map, meta] = sections;
+    Ok(codegen::static_bitmap_expr(
+        [gfx?, palette?, map?, meta?],
+        &bitmap.spec,
+    ))
+}
+
+/// Writes `data` to `$OUT_DIR/{file}` and returns an aligned `include_bytes!` expression for it.
+fn write_section(out_dir: &Path, file: &str, data: &[u8]) -> Result<TokenStream> {
+    if data.is_empty() {
+        return Ok(quote! { &[] });
+    }
+
+    let path = out_dir.join(file);
+    std::fs::write(&path, data).map_err(|e| Error::Io(format!("{}: {e}", path.display())))?;
+
+    let file = format!("/{file}");
+    Ok(codegen::aligned(
+        data.len(),
+        quote! { *include_bytes!(concat!(env!("OUT_DIR"), #file)) },
+    ))
+}
@@ -0,0 +1,299 @@
+//! Rust code generation for converted bitmaps.
+//!
+//! The `grit!` macro, [`build`](crate::build) and the command line tool all emit their code
+//! through these functions. The generated code refers to the types through the `::rgrit` crate.
+
+use crate::{Bitmap, SpriteSheet};
+use proc_macro2::{Literal, TokenStream};
+use quote::{format_ident, quote};
+use rgrit_core::*;
+
+/// Formats generated items as Rust source.
+pub fn source(items: TokenStream) -> String {
+    let file = syn::parse2(items).expect("generated code is valid Rust");
+    prettyplease::unparse(&file)
+}
+
+/// A `pub static {name}: {ty} = {expr};` item.
+pub fn item(name: &str, ty: TokenStream, expr: TokenStream) -> TokenStream {
+    let name = format_ident!("{name}");
+    quote! { pub static #name: #ty = #expr; }
+}
+
+/// A `pub static` item holding `bitmap` as a `::rgrit::StaticBitmap`.
+pub fn static_bitmap(name: &str, bitmap: &Bitmap) -> TokenStream {
+    let sections = [&bitmap.gfx, &bitmap.palette, &bitmap.map, &bitmap.meta].map(|s| bytes(s));
+    item(
+        name,
+        quote! { ::rgrit::StaticBitmap },
+        static_bitmap_expr(sections, &bitmap.spec),
+    )
+}
+
+/// A `pub static` item holding `data` as a 4-byte aligned `&[u8]`.
+pub fn static_bytes(name: &str, data: &[u8]) -> TokenStream {
+    item(name, quote! { &[u8] }, bytes(data))
+}
+
+/// A 4-byte aligned expression of type `&'static [u8]` for the `[u8; len]` expression `value`.
+///
+/// The data lives in its own `static`, so it's stored once however often the expression is used,
+/// and the expression works in any context, not just in `const` and `static` initializers.
+pub fn aligned(len: usize, value: TokenStream) -> TokenStream {
+    if len == 0 {
+        return quote! { &[] };
+    }
+
+    quote! {
+        {
+            static DATA: ::rgrit::Aligned<[u8; #len]> = ::rgrit::Aligned(#value);
+            &DATA.0
+        }
+    }
+}
+
+/// An [`aligned`] expression with `data` as an array literal.
+pub fn bytes(data: &[u8]) -> TokenStream {
+    let bytes = data.iter().map(|byte| Literal::u8_unsuffixed(*byte));
+    aligned(data.len(), quote! { [#(#bytes),*] })
+}
+
+/// A `::rgrit::StaticBitmap` expression from the expressions for its gfx, palette, map and meta
+/// slices.
+pub fn static_bitmap_expr(sections: [TokenStream; 4], spec: &BitmapSpec) -> TokenStream {
+    let [gfx, palette, map, meta] = sections;
+    let spec = self::spec(spec);
+
+    quote! {
+        ::rgrit::StaticBitmap {
+            gfx: #gfx,
+            palette: #palette,
+            map: #map,
+            meta: #meta,
+            spec: #spec,
+        }
+    }
+}
+
+/// A `::rgrit::StaticBatch` expression from the expressions for the shared palette and the
+/// bitmaps.
+pub fn static_batch_expr(palette: TokenStream, bitmaps: &[TokenStream]) -> TokenStream {
+    let count = bitmaps.len();
+
+    // The bitmaps borrow statics, so the array has to be one too
+    quote! {
+        {
+            static BITMAPS: [::rgrit::StaticBitmap; #count] = [#(#bitmaps),*];
+            ::rgrit::StaticBatch {
+                palette: #palette,
+                bitmaps: &BITMAPS,
+            }
+        }
+    }
+}
+
+/// A `::rgrit::StaticSpriteSheet` expression from the expressions for its tiles and palette
+/// slices.
+pub fn static_sprite_sheet_expr(sections: [TokenStream; 2], sheet: &SpriteSheet) -> TokenStream {
+    let [tiles, palette] = sections;
+    let frames = sheet.frames.iter().map(
+        |Frame {
+             tile,
+             width,
+             height,
+         }| {
+            quote! { ::rgrit::Frame { tile: #tile, width: #width, height: #height } }
+        },
+    );
+    let mapping = match sheet.mapping {
+        ObjMapping::OneD => quote! { ::rgrit::ObjMapping::OneD },
+        ObjMapping::TwoD => quote! { ::rgrit::ObjMapping::TwoD },
+    };
+    let spec = spec(&sheet.spec);
+
+    quote! {
+        ::rgrit::StaticSpriteSheet {
+            tiles: #tiles,
+            palette: #palette,
+            frames: &[#(#frames),*],
+            mapping: #mapping,
+            spec: #spec,
+        }
+    }
+}
+
+/// An expression of type `::rgrit::BitmapSpec` for `spec`.
+pub fn spec(spec: &BitmapSpec) -> TokenStream {
+    // Also put some metadata so we can automatically display it.
+    let bit_depth = match spec.bit_depth {
+        Some(BitDepth::A3I5) => quote! { Some(::rgrit::BitDepth::A3I5) },
+        Some(BitDepth::A5I3) => quote! { Some(::rgrit::BitDepth::A5I3) },
+        Some(BitDepth::FourByFour) => quote! { Some(::rgrit::BitDepth::FourByFour) },
+        Some(BitDepth::Custom(n)) => quote! { Some(::rgrit::BitDepth::Custom(#n)) },
+
+        None => quote! { None },
+    };
+    let format = match spec.format {
+        GfxFormat::Bitmap => quote! { ::rgrit::GfxFormat::Bitmap },
+        GfxFormat::Tile => quote! { ::rgrit::GfxFormat::Tile },
+    };
+    let transparency = transparency(spec.transparency);
+    let palette_range = match spec.palette_range {
+        Some(PaletteRange { start, end }) => {
+            quote! { Some(::rgrit::PaletteRange { start: #start, end: #end }) }
+        }
+        None => quote! { None },
+    };
+    let map_reduction = {
+        let MapReduction {
+            tiles,
+            flips,
+            palettes,
+        } = spec.map_reduction;
+        quote! {
+            ::rgrit::MapReduction { tiles: #tiles, flips: #flips, palettes: #palettes }
+        }
+    };
+    let map_layout = match spec.map_layout {
+        MapLayout::Flat => quote! { ::rgrit::MapLayout::Flat },
+        MapLayout::ScreenBlock => quote! { ::rgrit::MapLayout::ScreenBlock },
+        MapLayout::Affine => quote! { ::rgrit::MapLayout::Affine },
+    };
+    let map_entry_format = {
+        let MapEntryFormat {
+            bits,
+            index,
+            hflip,
+            vflip,
+            palette,
+        } = spec.map_entry_format;
+        let [index, hflip, vflip, palette] = [index, hflip, vflip, palette].map(|field| {
+            let MapField { shift, len } = field;
+            quote! { ::rgrit::MapField::new(#shift, #len) }
+        });
+        quote! {
+            ::rgrit::MapEntryFormat {
+                bits: #bits,
+                index: #index,
+                hflip: #hflip,
+                vflip: #vflip,
+                palette: #palette,
+            }
+        }
+    };
+    let sections = {
+        let Sections { gfx, palette, map } = spec.sections;
+        quote! { ::rgrit::Sections { gfx: #gfx, palette: #palette, map: #map } }
+    };
+    let gfx_compression = compression(spec.gfx_compression);
+    let palette_compression = compression(spec.palette_compression);
+    let map_compression = compression(spec.map_compression);
+    let gfx_data_type = data_type(spec.gfx_data_type);
+    let palette_data_type = data_type(spec.palette_data_type);
+    let map_data_type = data_type(spec.map_data_type);
+    let texture = match spec.texture {
+        Some(TextureSpec {
+            format,
+            width,
+            height,
+            transparent,
+        }) => {
+            let format = format_ident!("{format:?}");
+            quote! {
+                Some(::rgrit::TextureSpec {
+                    format: ::rgrit::TextureFormat::#format,
+                    width: #width,
+                    height: #height,
+                    transparent: #transparent,
+                })
+            }
+        }
+        None => quote! { None },
+    };
+
+    let BitmapSpec {
+        gfx_offset,
+        map_offset,
+        source_width,
+        source_height,
+        width,
+        height,
+        bpp,
+        tile_width,
+        tile_height,
+        meta_width,
+        meta_height,
+        tile_count,
+        map_count,
+        palette_count,
+        ..
+    } = spec;
+
+    quote! {
+        ::rgrit::BitmapSpec {
+            bit_depth: #bit_depth,
+            format: #format,
+            transparency: #transparency,
+            palette_range: #palette_range,
+            map_reduction: #map_reduction,
+            map_layout: #map_layout,
+            map_entry_format: #map_entry_format,
+            gfx_compression: #gfx_compression,
+            palette_compression: #palette_compression,
+            map_compression: #map_compression,
+            gfx_data_type: #gfx_data_type,
+            palette_data_type: #palette_data_type,
+            map_data_type: #map_data_type,
+            sections: #sections,
+            gfx_offset: #gfx_offset,
+            map_offset: #map_offset,
+            texture: #texture,
+            source_width: #source_width,
+            source_height: #source_height,
+            width: #width,
+            height: #height,
+            bpp: #bpp,
+            tile_width: #tile_width,
+            tile_height: #tile_height,
+            meta_width: #meta_width,
+            meta_height: #meta_height,
+            tile_count: #tile_count,
+            map_count: #map_count,
+            palette_count: #palette_count,
+        }
+    }
+}
+
+fn transparency(transparency: Transparency) -> TokenStream {
+    match transparency {
+        Transparency::Disabled => quote! { ::rgrit::Transparency::Disabled },
+        Transparency::Color(Color::RGB { r, g, b }) => {
+            quote! { ::rgrit::Transparency::Color(::rgrit::Color::RGB { r: #r, g: #g, b: #b }) }
+        }
+        Transparency::Color(Color::Bgr555(Bgr555(clr))) => {
+            quote! { ::rgrit::Transparency::Color(::rgrit::Color::Bgr555(::rgrit::Bgr555(#clr))) }
+        }
+        Transparency::FirstPaletteEntry => {
+            quote! { ::rgrit::Transparency::FirstPaletteEntry }
+        }
+        Transparency::TopLeftPixel => quote! { ::rgrit::Transparency::TopLeftPixel },
+    }
+}
+
+fn compression(compression: Compression) -> TokenStream {
+    match compression {
+        Compression::Off => quote! { ::rgrit::Compression::Off },
+        Compression::LZ77 => quote! { ::rgrit::Compression::LZ77 },
+        Compression::Huffman => quote! { ::rgrit::Compression::Huffman },
+        Compression::RLE => quote! { ::rgrit::Compression::RLE },
+        Compression::OffHeader => quote! { ::rgrit::Compression::OffHeader },
+    }
+}
+
+fn data_type(data_type: DataType) -> TokenStream {
+    match data_type {
+        DataType::U8 => quote! { ::rgrit::DataType::U8 },
+        DataType::U16 => quote! { ::rgrit::DataType::U16 },
+        DataType::U32 => quote! { ::rgrit::DataType::U32 },
+    }
+}
@@ -0,0 +1,53 @@
+//! Writes bitmaps as GRF files, see [`rgrit_core::grf`].
+
+use crate::Bitmap;
+use rgrit_core::{grf, Compression};
+
+impl Bitmap {
+    /// Bundles the sections into a GRF file grit's loaders can read, with the rest of the spec
+    /// in an `RGRT` chunk for [`rgrit_core::grf::Grf`].
+    ///
+    /// Empty sections are left out.
+    pub fn to_grf(&self) -> Vec<u8> {
+        let spec = &self.spec;
+        let meta = !self.meta.is_empty();
+        let header = grf::header(spec, !self.map.is_empty(), meta);
+        let extension = grf::spec(spec);
+
+        // The RIFF size is filled in once everything is written
+        let mut out = b"RIFF\0\0\0\0GRF ".to_vec();
+        for (id, data, compression) in [
+            (b"HDR ", &header[..], None),
+            (b"RGRT", &extension[..], None),
+            (b"GFX ", &self.gfx, Some(spec.gfx_compression)),
+            (b"PAL ", &self.palette, Some(spec.palette_compression)),
+            (
+                if meta { b"MMAP" } else { b"MAP " },
+                &self.map,
+                Some(spec.map_compression),
+            ),
+            (b"MTIL", &self.meta, Some(spec.map_compression)),
+        ] {
+            if data.is_empty() {
+                continue;
+            }
+
+            // Compressed sections already start with their header
+            let data_header = match compression {
+                Some(Compression::Off) => &grf::data_header(data.len())[..],
+                _ => &[],
+            };
+            let size = data_header.len() + data.len();
+
+            out.extend_from_slice(id);
+            out.extend_from_slice(&(size as u32).to_le_bytes());
+            out.extend_from_slice(data_header);
+            out.extend_from_slice(data);
+            out.resize(out.len().next_multiple_of(4), 0);
+        }
+
+        let size = (out.len() - 8) as u32;
+        out[4..8].copy_from_slice(&size.to_le_bytes());
+        out
+    }
+}
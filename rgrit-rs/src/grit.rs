@@ -0,0 +1,103 @@
+//! An owned wrapper around grit's `GRIT_REC`.
+
+use crate::{ffi_lock, log, Error, Result, Source};
+use rgrit_sys::{dib_free, grit_alloc, grit_clear, grit_free, grit_init, grit_init_from_dib};
+use rgrit_sys::{grit_run, GritRec, CLDIB};
+use std::ffi::{c_char, CString};
+use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+
+/// A grit record with its source image loaded.
+///
+/// Dropping it frees the record and the image. Strings grit points into are owned by this
+/// wrapper and unhooked before `grit_free`, which would otherwise try to `free()` them. Calls
+/// into grit take the FFI lock, setting up the record in between doesn't need it.
+pub(crate) struct Grit {
+    rec: NonNull<GritRec>,
+    dib: *mut CLDIB,
+    path: Option<CString>,
+    symbol: Box<[c_char; 256]>,
+}
+
+impl Grit {
+    /// Allocates a record and loads `source` into it.
+    pub(crate) fn new(source: &Source) -> Result<Grit> {
+        let rec = {
+            let _ffi = ffi_lock();
+            let rec = NonNull::new(unsafe { grit_alloc() })
+                .ok_or_else(|| Error::ConversionError(source.name()))?;
+            unsafe {
+                grit_clear(rec.as_ptr());
+                grit_init(rec.as_ptr());
+            }
+            rec
+        };
+
+        let mut grit = Grit {
+            rec,
+            dib: std::ptr::null_mut(),
+            path: None,
+            symbol: Box::new([0; 256]),
+        };
+
+        grit.dib = source.load(&mut grit.path)?;
+
+        let path = grit.path.as_ref().map_or(std::ptr::null(), |p| p.as_ptr());
+        let symbol = grit.symbol.as_mut_ptr();
+        let dib = grit.dib;
+        grit.srcPath = path as *mut c_char;
+        grit.symName = symbol;
+        grit.srcDib = dib;
+
+        let _ffi = ffi_lock();
+        unsafe { grit_init_from_dib(grit.rec.as_ptr()) };
+
+        Ok(grit)
+    }
+
+    /// Runs the conversion, the results end up in the `_*Rec` records.
+    ///
+    /// Returns the messages grit logged, as warnings if the conversion succeeds and as the cause
+    /// if it fails.
+    pub(crate) fn run(&mut self) -> Result<Vec<String>, Vec<String>> {
+        let rec = self.rec.as_ptr();
+        let _ffi = ffi_lock();
+        let (ok, log) = log::capture(|| unsafe { grit_run(rec) });
+
+        match ok {
+            true => Ok(log::messages(&log)),
+            false => Err(log::messages(&log)),
+        }
+    }
+}
+
+impl Deref for Grit {
+    type Target = GritRec;
+
+    fn deref(&self) -> &GritRec {
+        unsafe { self.rec.as_ref() }
+    }
+}
+
+impl DerefMut for Grit {
+    fn deref_mut(&mut self) -> &mut GritRec {
+        unsafe { self.rec.as_mut() }
+    }
+}
+
+impl Drop for Grit {
+    fn drop(&mut self) {
+        // [`grit_free`] frees a bunch of nested pointers, null the ones grit doesn't own
+        self.srcDib = std::ptr::null_mut();
+        self.srcPath = std::ptr::null_mut();
+        self.symName = std::ptr::null_mut();
+
+        let _ffi = ffi_lock();
+        unsafe {
+            grit_free(self.rec.as_ptr());
+            if !self.dib.is_null() {
+                dib_free(self.dib);
+            }
+        }
+    }
+}
@@ -1,16 +1,45 @@
+extern crate proc_macro;
+
 use rgrit_core::*;
+use rgrit_core::{decode, decompress};
 use std::ffi::{CString, NulError};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+pub mod build;
+pub mod codegen;
+mod grf;
+mod grit;
+mod log;
+mod sprite;
+mod validate;
+
+use grit::Grit;
+pub use sprite::{SpriteSheet, SpriteSheetBuilder};
+pub use validate::Setting;
 
 use rgrit_sys::EGritCompression_GRIT_CPRS_HEADER;
 use rgrit_sys::EGritCompression_GRIT_CPRS_HUFF;
 use rgrit_sys::EGritCompression_GRIT_CPRS_LZ77;
 use rgrit_sys::EGritCompression_GRIT_CPRS_OFF;
 use rgrit_sys::EGritCompression_GRIT_CPRS_RLE;
+use rgrit_sys::EGritDataType_GRIT_U16;
+use rgrit_sys::EGritDataType_GRIT_U32;
+use rgrit_sys::EGritDataType_GRIT_U8;
+use rgrit_sys::EGritMapLayout_GRIT_MAP_AFFINE;
+use rgrit_sys::EGritMapLayout_GRIT_MAP_FLAT;
+use rgrit_sys::EGritMapLayout_GRIT_MAP_REG;
+use rgrit_sys::EGritMapReduction_GRIT_RDX_FLIP;
+use rgrit_sys::EGritMapReduction_GRIT_RDX_OFF;
+use rgrit_sys::EGritMapReduction_GRIT_RDX_PBANK;
+use rgrit_sys::EGritMapReduction_GRIT_RDX_TILE;
+use rgrit_sys::EGritProcMode_GRIT_EXCLUDE;
+use rgrit_sys::EGritProcMode_GRIT_EXPORT;
 use rgrit_sys::{
-    cldib_load, grit_alloc, grit_clear, grit_free, grit_init, grit_init_from_dib, grit_run,
-    tagRGBQUAD, EGritGraphicsMode_GRIT_GFX_BMP_A, EGritGraphicsTextureFormat_GRIT_TEXFMT_4x4,
-    EGritGraphicsTextureFormat_GRIT_TEXFMT_A3I5, EGritGraphicsTextureFormat_GRIT_TEXFMT_A5I3,
-    RECORD,
+    cldib_load, dib_alloc, dib_convert_copy, dib_free, dib_get_bpp, dib_get_height, dib_get_img,
+    dib_get_pal, dib_get_pitch, dib_get_width, tagRGBQUAD, EGritGraphicsMode_GRIT_GFX_BMP_A,
+    EGritGraphicsTextureFormat_GRIT_TEXFMT_4x4, EGritGraphicsTextureFormat_GRIT_TEXFMT_A3I5,
+    EGritGraphicsTextureFormat_GRIT_TEXFMT_A5I3, CLDIB, RECORD,
 };
 
 #[derive(Clone, Debug, thiserror::Error)]
@@ -23,13 +52,373 @@ pub enum Error {
     InputNotFound(String),
     #[error("Unable to convert input file: {0}")]
     ConversionError(String),
+    /// grit refused the input, `messages` holds what it logged.
+    #[error("grit was unable to convert {input}: {}", .messages.join(", "))]
+    Grit {
+        input: String,
+        messages: Vec<String>,
+    },
+    #[error("A {width}x{height} image has no pixels")]
+    EmptyImage { width: u32, height: u32 },
+    #[error("Expected {expected} bytes of image data, got {actual}")]
+    BadBufferSize { expected: usize, actual: usize },
+    #[error("Palette has {0} colors, at most 256 are supported")]
+    BadPalette(usize),
+    #[error("{0} has no palette to take the transparent color from")]
+    NoPalette(String),
+    #[error("Unable to allocate a {width}x{height} bitmap")]
+    AllocationFailed { width: u32, height: u32 },
+    #[error("{setting} = {size} is not a multiple of 8")]
+    BadTileSize { setting: Setting, size: u8 },
+    #[error("{setting} = {size} doesn't evenly divide the {tiles} tiles of the area")]
+    BadMetaSize {
+        setting: Setting,
+        size: u8,
+        tiles: u32,
+    },
+    #[error("{0} = 0, metatiles are at least one tile")]
+    ZeroMetaSize(Setting),
+    #[error("{setting} = {value} is outside of the {size} pixel image")]
+    AreaOutOfBounds {
+        setting: Setting,
+        value: i32,
+        size: u32,
+    },
+    #[error("{setting} = {value} is outside of the 256 color palette")]
+    BadPaletteRange { setting: Setting, value: u16 },
+    #[error(
+        "{setting} makes the texture {size} texels, textures are a power of two from 8 to 1024"
+    )]
+    BadTextureSize { setting: Setting, size: u32 },
+    #[error("Invalid map_entry_format: {0}")]
+    BadMapEntryFormat(MapEntryFormatError),
+    #[error("{setting} = {size} is not a multiple of 8 up to the {image} pixel image")]
+    BadFrameSize {
+        setting: Setting,
+        size: u32,
+        image: u32,
+    },
+    #[error(
+        "{width}x{height} is not an OBJ size, frames are 8x8 to 64x64 with a 1:1, 2:1 or 4:1 ratio"
+    )]
+    BadFrameShape { width: u32, height: u32 },
+    #[error("frame_count = {count}, but the sheet holds {frames} frames")]
+    BadFrameCount { count: u32, frames: u32 },
+    #[error("Unable to slice the bitmap into frames: {0}")]
+    NotSpriteSheet(&'static str),
+    #[error("{setting} conflicts with {other}: {reason}")]
+    Conflict {
+        setting: Setting,
+        other: Setting,
+        reason: &'static str,
+    },
+    #[error("Converting {input} panicked: {message}")]
+    Panicked { input: String, message: String },
+    #[error("I/O error: {0}")]
+    Io(String),
+    #[error("Unable to decode bitmap: {0}")]
+    Decode(rgrit_core::decode::DecodeError),
+}
+
+impl Error {
+    /// The settings a validation error is about, the one to blame first.
+    pub fn settings(&self) -> Vec<Setting> {
+        match self {
+            Error::BadBitDepth(_) => vec![Setting::BitDepth],
+            Error::NoPalette(_) => vec![Setting::Transparency],
+            Error::BadMapEntryFormat(_) => vec![Setting::MapEntryFormat],
+            Error::BadFrameCount { .. } => vec![Setting::FrameCount],
+            Error::BadFrameShape { .. } => vec![Setting::FrameWidth, Setting::FrameHeight],
+            Error::ZeroMetaSize(setting) => vec![*setting],
+            Error::BadTileSize { setting, .. }
+            | Error::BadMetaSize { setting, .. }
+            | Error::AreaOutOfBounds { setting, .. }
+            | Error::BadPaletteRange { setting, .. }
+            | Error::BadTextureSize { setting, .. }
+            | Error::BadFrameSize { setting, .. } => vec![*setting],
+            Error::Conflict { setting, other, .. } => vec![*setting, *other],
+            _ => Vec::new(),
+        }
+    }
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
+#[derive(Clone, Debug)]
+enum Source {
+    Path(String),
+    Rgba {
+        width: u32,
+        height: u32,
+        data: Vec<u8>,
+    },
+    Indexed {
+        width: u32,
+        height: u32,
+        data: Vec<u8>,
+        palette: Vec<Color>,
+    },
+}
+
+impl Default for Source {
+    fn default() -> Self {
+        Source::Path(String::new())
+    }
+}
+
+impl Source {
+    /// A human readable name for the source, used in error messages.
+    fn name(&self) -> String {
+        match self {
+            Source::Path(path) => path.clone(),
+            Source::Rgba { width, height, .. } => format!("<{width}x{height} RGBA buffer>"),
+            Source::Indexed { width, height, .. } => format!("<{width}x{height} indexed buffer>"),
+        }
+    }
+
+    /// Loads the source into a freshly allocated [`CLDIB`].
+    ///
+    /// For [`Source::Path`] the path is also stored in `src` so it can be handed to grit. Only
+    /// decoding a file holds the FFI lock, in-memory pixels are copied without it.
+    fn load(&self, src: &mut Option<CString>) -> Result<*mut CLDIB> {
+        match self {
+            Source::Path(path) => {
+                let path = src.insert(CString::new(path.as_bytes())?);
+                let dib = {
+                    let _ffi = ffi_lock();
+                    unsafe { cldib_load(path.as_ptr(), std::ptr::null_mut()) }
+                };
+
+                if dib.is_null() {
+                    return Err(Error::InputNotFound(self.name()));
+                }
+
+                Ok(dib)
+            }
+            Source::Rgba {
+                width,
+                height,
+                data,
+            } => {
+                let dib = alloc_dib(*width, *height, 32)?;
+                // cldib stores true color pixels as BGRA
+                copy_rows(dib, data, *width as usize * 4, |row, out| {
+                    for (src, dst) in row.chunks_exact(4).zip(out.chunks_exact_mut(4)) {
+                        dst.copy_from_slice(&[src[2], src[1], src[0], src[3]]);
+                    }
+                });
+
+                Ok(dib)
+            }
+            Source::Indexed {
+                width,
+                height,
+                data,
+                palette,
+            } => {
+                let dib = alloc_dib(*width, *height, 8)?;
+                copy_rows(dib, data, *width as usize, |row, out| {
+                    out[..row.len()].copy_from_slice(row)
+                });
+
+                let pal = unsafe { std::slice::from_raw_parts_mut(dib_get_pal(dib), 256) };
+                for (entry, color) in pal.iter_mut().zip(palette) {
+                    *entry = rgb_quad(*color);
+                }
+
+                Ok(dib)
+            }
+        }
+    }
+}
+
+/// Serialises every call into libgrit, cldib and FreeImage.
+///
+/// grit keeps its log and export state in globals and FreeImage's plugin registry isn't
+/// guarded either, so two conversions must never overlap, not even from different threads.
+/// The lock is only held around the calls themselves. Filling in a record or a [`CLDIB`] and
+/// reading the results back is plain memory access, and so are cldib's `dib_get_*` accessors.
+static FFI: Mutex<()> = Mutex::new(());
+
+fn ffi_lock() -> MutexGuard<'static, ()> {
+    // A panic while converting leaves nothing behind that the next conversion depends on
+    FFI.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Loads an image file and returns its dimensions and RGBA8888 pixels.
+fn load_rgba(path: &str) -> Result<(u32, u32, Vec<u8>)> {
+    let source = Source::Path(path.to_string());
+    let dib = source.load(&mut None)?;
+    let rgba = {
+        let _ffi = ffi_lock();
+        unsafe { dib_convert_copy(dib, 32, 0) }
+    };
+    free_dib(dib);
+
+    if rgba.is_null() {
+        return Err(Error::ConversionError(source.name()));
+    }
+
+    let width = unsafe { dib_get_width(rgba) } as u32;
+    let height = unsafe { dib_get_height(rgba) } as u32;
+    let pitch = unsafe { dib_get_pitch(rgba) } as usize;
+    let img = unsafe { dib_get_img(rgba) };
+
+    let mut data = Vec::with_capacity(width as usize * height as usize * 4);
+    for y in 0..height as usize {
+        let row = unsafe { std::slice::from_raw_parts(img.add(y * pitch), width as usize * 4) };
+        for px in row.chunks_exact(4) {
+            data.extend_from_slice(&[px[2], px[1], px[0], px[3]]);
+        }
+    }
+
+    free_dib(rgba);
+
+    Ok((width, height, data))
+}
+
+fn alloc_dib(width: u32, height: u32, bpp: i32) -> Result<*mut CLDIB> {
+    let dib = {
+        let _ffi = ffi_lock();
+        unsafe { dib_alloc(width as i32, height as i32, bpp, std::ptr::null()) }
+    };
+
+    if dib.is_null() {
+        Err(Error::AllocationFailed { width, height })
+    } else {
+        Ok(dib)
+    }
+}
+
+fn free_dib(dib: *mut CLDIB) {
+    let _ffi = ffi_lock();
+    unsafe { dib_free(dib) };
+}
+
+/// Copies tightly packed rows of `stride` bytes into the (padded) rows of a [`CLDIB`].
+fn copy_rows(dib: *mut CLDIB, data: &[u8], stride: usize, mut f: impl FnMut(&[u8], &mut [u8])) {
+    let pitch = unsafe { dib_get_pitch(dib) } as usize;
+    let img = unsafe { dib_get_img(dib) };
+
+    for (y, row) in data.chunks_exact(stride).enumerate() {
+        let out = unsafe { std::slice::from_raw_parts_mut(img.add(y * pitch), pitch) };
+        f(row, out);
+    }
+}
+
+fn compression_value(compression: Compression) -> u8 {
+    match compression {
+        Compression::Off => EGritCompression_GRIT_CPRS_OFF as u8,
+        Compression::LZ77 => EGritCompression_GRIT_CPRS_LZ77 as u8,
+        Compression::Huffman => EGritCompression_GRIT_CPRS_HUFF as u8,
+        Compression::RLE => EGritCompression_GRIT_CPRS_RLE as u8,
+        Compression::OffHeader => EGritCompression_GRIT_CPRS_HEADER as u8,
+    }
+}
+
+fn compression_from_value(value: u8) -> Compression {
+    [
+        Compression::LZ77,
+        Compression::Huffman,
+        Compression::RLE,
+        Compression::OffHeader,
+    ]
+    .into_iter()
+    .find(|compression| compression_value(*compression) == value)
+    .unwrap_or(Compression::Off)
+}
+
+/// Size of a section before compression, compressed sections carry it in their header.
+fn raw_size(data: &[u8], compression: Compression) -> usize {
+    match compression {
+        Compression::Off => data.len(),
+        _ => decompress::Header::parse(data).map_or(data.len(), |header| header.size),
+    }
+}
+
+fn data_type_value(data_type: DataType) -> u8 {
+    match data_type {
+        DataType::U8 => EGritDataType_GRIT_U8 as u8,
+        DataType::U16 => EGritDataType_GRIT_U16 as u8,
+        DataType::U32 => EGritDataType_GRIT_U32 as u8,
+    }
+}
+
+/// Pads `data` with zeroes to a multiple of the size of `data_type`.
+fn pad(mut data: Vec<u8>, data_type: DataType) -> Vec<u8> {
+    data.resize(data.len().next_multiple_of(data_type.size()), 0);
+    data
+}
+
+/// Reads the transparent color for [`Transparency::FirstPaletteEntry`] and
+/// [`Transparency::TopLeftPixel`] from the source image.
+///
+/// # Safety
+/// `dib` has to point to a valid [`CLDIB`].
+unsafe fn source_color(dib: *mut CLDIB, transparency: Transparency) -> Option<Color> {
+    let bpp = dib_get_bpp(dib);
+    let img = dib_get_img(dib);
+    let pal = dib_get_pal(dib);
+    let entry = |index: usize| {
+        let quad = *pal.add(index);
+        Color::RGB {
+            r: quad.rgbRed,
+            g: quad.rgbGreen,
+            b: quad.rgbBlue,
+        }
+    };
+
+    match (transparency, bpp) {
+        (Transparency::FirstPaletteEntry, 1..=8) if !pal.is_null() => Some(entry(0)),
+        // Paletted pixels are packed with the leftmost one in the high bits
+        (Transparency::TopLeftPixel, 1..=8) if !pal.is_null() => {
+            Some(entry((*img >> (8 - bpp)) as usize))
+        }
+        (Transparency::TopLeftPixel, 16) => {
+            Some(Color::Bgr555(Bgr555::from_le_bytes([*img, *img.add(1)])))
+        }
+        (Transparency::TopLeftPixel, 24 | 32) => Some(Color::RGB {
+            r: *img.add(2),
+            g: *img.add(1),
+            b: *img,
+        }),
+        _ => None,
+    }
+}
+
+/// Loads an image file and reads its transparent color, see [`source_color`].
+fn load_color(path: &str, transparency: Transparency) -> Result<Color> {
+    let source = Source::Path(path.to_string());
+    let dib = source.load(&mut None)?;
+    let color = unsafe { source_color(dib, transparency) };
+    free_dib(dib);
+
+    color.ok_or_else(|| Error::NoPalette(source.name()))
+}
+
+fn rgb_quad(color: Color) -> tagRGBQUAD {
+    match color {
+        Color::RGB { r, g, b } => tagRGBQUAD {
+            rgbBlue: b,
+            rgbGreen: g,
+            rgbRed: r,
+            rgbReserved: 0,
+        },
+        Color::Bgr555(clr) => {
+            let (r, g, b) = clr.to_rgb888();
+            tagRGBQUAD {
+                rgbBlue: b,
+                rgbGreen: g,
+                rgbRed: r,
+                rgbReserved: 0,
+            }
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct BitmapBuilder {
-    input: String,
+    source: Source,
     format: Option<GfxFormat>,
     tile_width: Option<u8>,
     tile_height: Option<u8>,
@@ -38,6 +427,26 @@ pub struct BitmapBuilder {
     bit_depth_override: Option<BitDepth>,
     transparency: Option<Transparency>,
     compression: Option<Compression>,
+    gfx_compression: Option<Compression>,
+    palette_compression: Option<Compression>,
+    map_compression: Option<Compression>,
+    data_type: Option<DataType>,
+    gfx_data_type: Option<DataType>,
+    palette_data_type: Option<DataType>,
+    map_data_type: Option<DataType>,
+    map_reduction: Option<MapReduction>,
+    map_layout: Option<MapLayout>,
+    map_entry_format: Option<MapEntryFormat>,
+    gfx_offset: Option<u32>,
+    map_offset: Option<u32>,
+    texture: Option<bool>,
+    gfx: Option<bool>,
+    palette: Option<bool>,
+    map: Option<bool>,
+
+    palette_start: Option<u16>,
+    palette_end: Option<u16>,
+    palette_count: Option<u16>,
 
     area_left: Option<i32>,
     area_right: Option<i32>,
@@ -54,6 +463,97 @@ pub struct Bitmap {
     pub map: Vec<u8>,
     pub meta: Vec<u8>,
     pub spec: BitmapSpec,
+    /// What grit logged during the conversion, e.g. that colors were merged.
+    pub warnings: Vec<String>,
+}
+
+impl Bitmap {
+    /// Renders the bitmap back to RGBA pixels, `spec.width * spec.height * 4` bytes.
+    pub fn decode(&self) -> Result<Vec<u8>> {
+        decode::decode(&self.gfx, &self.palette, &self.map, &self.meta, &self.spec)
+            .map_err(Error::Decode)
+    }
+
+    /// The palette colors, only meaningful for uncompressed palettes.
+    pub fn palette(&self) -> Palette<'_> {
+        Bgr555::iter_le(&self.palette)
+    }
+
+    /// The texel data, 4x4 index block and palette of an NDS texture, `None` for other bitmaps.
+    pub fn texture(&self) -> Option<Texture<'_>> {
+        Texture::new(&self.spec, &self.gfx, &self.palette)
+    }
+}
+
+/// Converts each builder on a pool of worker threads and returns the results in the same order.
+///
+/// The calls into grit still take turns, see [`BitmapBuilder::build`], everything around them
+/// runs in parallel. One failed input doesn't stop the others, not even one that panics.
+pub fn build_all(builders: &[BitmapBuilder]) -> Vec<Result<Bitmap>> {
+    let threads = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(builders.len());
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(
+        std::iter::repeat_with(|| None)
+            .take(builders.len())
+            .collect::<Vec<_>>(),
+    );
+
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(builder) = builders.get(i) else {
+                    break;
+                };
+
+                let result = std::panic::catch_unwind(|| builder.build())
+                    .unwrap_or_else(|panic| Err(panicked(builder, panic)));
+                results.lock().unwrap_or_else(PoisonError::into_inner)[i] = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap_or_else(PoisonError::into_inner)
+        .into_iter()
+        .map(|result| result.expect("every input is converted by a worker"))
+        .collect()
+}
+
+/// Turns the payload of a panic while converting `builder` into an error.
+fn panicked(builder: &BitmapBuilder, panic: Box<dyn std::any::Any + Send>) -> Error {
+    let message = match panic.downcast::<String>() {
+        Ok(message) => *message,
+        Err(panic) => panic
+            .downcast_ref::<&str>()
+            .map_or("unknown panic", |message| message)
+            .to_string(),
+    };
+
+    Error::Panicked {
+        input: builder.source.name(),
+        message,
+    }
+}
+
+/// Converts several images against one shared palette, like grit's `-pS`.
+///
+/// All inputs share the settings of one [`BitmapBuilder`], its own input is ignored. The shared
+/// palette holds every BGR555 color used by any input once, at most 256 of them.
+#[derive(Clone, Debug, Default)]
+pub struct BatchBuilder {
+    inputs: Vec<String>,
+    settings: BitmapBuilder,
+}
+
+#[derive(Clone, Debug)]
+pub struct Batch {
+    pub palette: Vec<u8>,
+    /// The converted images in input order, their `palette` is left empty.
+    pub bitmaps: Vec<Bitmap>,
 }
 
 /// # Safety
@@ -82,11 +582,79 @@ unsafe impl RecordExt for RECORD {
 impl BitmapBuilder {
     pub fn new(input: impl AsRef<str>) -> BitmapBuilder {
         BitmapBuilder {
-            input: input.as_ref().to_string(),
+            source: Source::Path(input.as_ref().to_string()),
             ..Default::default()
         }
     }
 
+    /// Replaces the input file while keeping all other settings.
+    pub fn with_input(mut self, input: impl AsRef<str>) -> Self {
+        self.source = Source::Path(input.as_ref().to_string());
+        self
+    }
+
+    /// Creates a builder from a tightly packed RGBA8888 buffer of `width * height` pixels.
+    ///
+    /// Fails with [`Error::EmptyImage`] if either side is 0.
+    pub fn from_rgba(width: u32, height: u32, data: &[u8]) -> Result<BitmapBuilder> {
+        if width == 0 || height == 0 {
+            return Err(Error::EmptyImage { width, height });
+        }
+
+        let expected = width as usize * height as usize * 4;
+        if data.len() != expected {
+            return Err(Error::BadBufferSize {
+                expected,
+                actual: data.len(),
+            });
+        }
+
+        Ok(BitmapBuilder {
+            source: Source::Rgba {
+                width,
+                height,
+                data: data.to_vec(),
+            },
+            ..Default::default()
+        })
+    }
+
+    /// Creates a builder from an 8-bit indexed buffer of `width * height` pixels and its palette.
+    ///
+    /// Fails with [`Error::EmptyImage`] if either side is 0.
+    pub fn from_indexed(
+        width: u32,
+        height: u32,
+        data: &[u8],
+        palette: &[Color],
+    ) -> Result<BitmapBuilder> {
+        if width == 0 || height == 0 {
+            return Err(Error::EmptyImage { width, height });
+        }
+
+        let expected = width as usize * height as usize;
+        if data.len() != expected {
+            return Err(Error::BadBufferSize {
+                expected,
+                actual: data.len(),
+            });
+        }
+
+        if palette.len() > 256 {
+            return Err(Error::BadPalette(palette.len()));
+        }
+
+        Ok(BitmapBuilder {
+            source: Source::Indexed {
+                width,
+                height,
+                data: data.to_vec(),
+                palette: palette.to_vec(),
+            },
+            ..Default::default()
+        })
+    }
+
     pub fn with_format(mut self, format: GfxFormat) -> Self {
         self.format = Some(format);
         self
@@ -117,16 +685,128 @@ impl BitmapBuilder {
         self
     }
 
+    /// Marks a 2, 4, 8 or 16bpp bitmap as NDS texture, see [`Bitmap::texture`].
+    ///
+    /// The A3I5, A5I3 and 4x4 bit depths are always textures.
+    pub fn with_texture(mut self, texture: bool) -> Self {
+        self.texture = Some(texture);
+        self
+    }
+
     pub fn with_transparency(mut self, transparency: Transparency) -> Self {
         self.transparency = Some(transparency);
         self
     }
 
+    /// Sets the compression of every section, the per-section setters take precedence.
     pub fn with_compression(mut self, compression: Compression) -> Self {
         self.compression = Some(compression);
         self
     }
 
+    pub fn with_gfx_compression(mut self, compression: Compression) -> Self {
+        self.gfx_compression = Some(compression);
+        self
+    }
+
+    pub fn with_palette_compression(mut self, compression: Compression) -> Self {
+        self.palette_compression = Some(compression);
+        self
+    }
+
+    pub fn with_map_compression(mut self, compression: Compression) -> Self {
+        self.map_compression = Some(compression);
+        self
+    }
+
+    /// Sets the data type of every section, the per-section setters take precedence.
+    pub fn with_data_type(mut self, data_type: DataType) -> Self {
+        self.data_type = Some(data_type);
+        self
+    }
+
+    pub fn with_gfx_data_type(mut self, data_type: DataType) -> Self {
+        self.gfx_data_type = Some(data_type);
+        self
+    }
+
+    pub fn with_palette_data_type(mut self, data_type: DataType) -> Self {
+        self.palette_data_type = Some(data_type);
+        self
+    }
+
+    pub fn with_map_data_type(mut self, data_type: DataType) -> Self {
+        self.map_data_type = Some(data_type);
+        self
+    }
+
+    /// Flips and palettes without tiles export the map without reducing it, and the spec
+    /// records [`MapReduction::NONE`].
+    pub fn with_map_reduction(mut self, map_reduction: MapReduction) -> Self {
+        self.map_reduction = Some(map_reduction);
+        self
+    }
+
+    pub fn with_map_layout(mut self, map_layout: MapLayout) -> Self {
+        self.map_layout = Some(map_layout);
+        self
+    }
+
+    /// Exports the graphics, or excludes them like grit's `-g!`, e.g. for a palette source image.
+    pub fn with_gfx(mut self, gfx: bool) -> Self {
+        self.gfx = Some(gfx);
+        self
+    }
+
+    /// Exports the palette, or excludes it like grit's `-p!`, e.g. when it's shared.
+    pub fn with_palette(mut self, palette: bool) -> Self {
+        self.palette = Some(palette);
+        self
+    }
+
+    /// Exports a map like grit's `-m`, or excludes it like `-m!`.
+    ///
+    /// Other map settings imply exporting one, so they can't be combined with excluding it.
+    pub fn with_map(mut self, map: bool) -> Self {
+        self.map = Some(map);
+        self
+    }
+
+    /// Packs map entries into a custom bit layout, like grit's `-mB`. Implies exporting a map.
+    pub fn with_map_entry_format(mut self, format: MapEntryFormat) -> Self {
+        self.map_entry_format = Some(format);
+        self
+    }
+
+    /// Adds `offset` to every non-zero pixel value, like grit's `-ga`.
+    pub fn with_gfx_offset(mut self, offset: u32) -> Self {
+        self.gfx_offset = Some(offset);
+        self
+    }
+
+    /// Adds `offset` to every non-zero map entry, like grit's `-ma`.
+    ///
+    /// Use it to point the map at tiles that are loaded behind other tiles in the same charblock.
+    pub fn with_map_offset(mut self, offset: u32) -> Self {
+        self.map_offset = Some(offset);
+        self
+    }
+
+    pub fn with_palette_start(mut self, palette_start: u16) -> Self {
+        self.palette_start = Some(palette_start);
+        self
+    }
+
+    pub fn with_palette_end(mut self, palette_end: u16) -> Self {
+        self.palette_end = Some(palette_end);
+        self
+    }
+
+    pub fn with_palette_count(mut self, palette_count: u16) -> Self {
+        self.palette_count = Some(palette_count);
+        self
+    }
+
     pub fn with_area_left(mut self, area_left: i32) -> Self {
         self.area_left = Some(area_left);
         self
@@ -157,27 +837,22 @@ impl BitmapBuilder {
         self
     }
 
+    /// Runs the conversion.
+    ///
+    /// This can be called from several threads at once. The native side isn't re-entrant, so
+    /// loading files and running grit take turns behind a process wide lock, while validation
+    /// and copying pixels in and out happen in parallel. See [`build_all`] for converting many
+    /// bitmaps at once.
     pub fn build(&self) -> Result<Bitmap> {
-        let gr = unsafe { grit_alloc() };
-        unsafe {
-            grit_clear(gr);
-            grit_init(gr);
-        }
-
-        let src = CString::new(self.input.as_bytes())?;
-
-        let gr = unsafe { &mut (*gr) };
+        self.validate()?;
 
-        gr.srcPath = src.as_ptr() as *mut std::ffi::c_char;
-        let dib = unsafe { cldib_load(gr.srcPath, std::ptr::null_mut()) };
-
-        if dib.is_null() {
-            return Err(Error::InputNotFound(self.input.clone()));
+        let mut grit = Grit::new(&self.source)?;
+        if let Source::Path(_) = self.source {
+            let (width, height) =
+                unsafe { (dib_get_width(grit.srcDib), dib_get_height(grit.srcDib)) };
+            self.validate_size(width as u32, height as u32)?;
         }
-
-        gr.srcDib = dib;
-
-        unsafe { grit_init_from_dib(gr) };
+        let gr = &mut *grit;
 
         match self.format.unwrap_or_default() {
             GfxFormat::Bitmap => {
@@ -201,126 +876,367 @@ impl BitmapBuilder {
                 BitDepth::FourByFour => {
                     gr.gfxTexMode = EGritGraphicsTextureFormat_GRIT_TEXFMT_4x4 as u8;
                 }
-                // Check if the bit depth is a power of two
-                BitDepth::Custom(n) if (*n & (*n - 1)) == 0 && *n > 0 && *n < 32 => {
-                    gr.gfxBpp = *n;
-                }
                 BitDepth::Custom(n) => {
-                    return Err(Error::BadBitDepth(*n));
+                    gr.gfxBpp = *n;
                 }
             }
         }
 
-        let area_left = self.area_left.unwrap_or(0);
-        let area_top = self.area_top.unwrap_or(0);
-        gr.areaLeft = area_left;
-        gr.areaTop = area_top;
+        gr.areaLeft = self.area_left.unwrap_or(0);
+        gr.areaTop = self.area_top.unwrap_or(0);
 
-        match (self.area_width, self.area_right) {
-            (Some(-1), Some(area_right)) if area_right != -1 => {
-                gr.areaRight = area_right;
-            }
-            (Some(area_width), _) if area_width != -1 => {
-                gr.areaRight = gr.areaLeft + area_width;
-            }
-            _ => {}
-        };
+        let (right, bottom) = self.area_edges();
+        if let Some(right) = right {
+            gr.areaRight = right.end;
+        }
+        if let Some(bottom) = bottom {
+            gr.areaBottom = bottom.end;
+        }
 
-        match (self.area_height, self.area_bottom) {
-            (Some(-1), Some(area_bottom)) if area_bottom != -1 => {
-                gr.areaBottom = area_bottom;
-            }
-            (Some(area_width), _) if area_width != -1 => {
-                gr.areaBottom = gr.areaTop + area_width;
+        // Mirrors grit's `-ps`, `-pe` and `-pn`, an explicit end wins over a count
+        let palette_range = match (self.palette_start, self.palette_end, self.palette_count) {
+            (None, None, None) => None,
+            (start, end, count) => {
+                let start = start.unwrap_or(gr.palStart as u16);
+                let end = match (end, count) {
+                    (Some(end), _) => end,
+                    (None, Some(count)) => start.saturating_add(count),
+                    (None, None) => gr.palEnd as u16,
+                };
+
+                gr.palStart = start as i32;
+                gr.palEnd = end as i32;
+
+                Some(PaletteRange { start, end })
             }
-            _ => {}
         };
 
         gr.metaWidth = self.meta_width.unwrap_or(1);
         gr.metaHeight = self.meta_height.unwrap_or(1);
 
-        match self.transparency.unwrap_or_default() {
+        let transparent = match self.transparency.unwrap_or_default() {
+            Transparency::Disabled => None,
+            Transparency::Color(color) => Some(color),
+            transparency => Some(
+                unsafe { source_color(gr.srcDib, transparency) }
+                    .ok_or_else(|| Error::NoPalette(self.source.name()))?,
+            ),
+        };
+
+        match transparent {
             // NDS only
-            Transparency::Disabled => {
+            None => {
                 gr.gfxMode = EGritGraphicsMode_GRIT_GFX_BMP_A as u8;
             }
-            Transparency::Color(Color::RGB { r, g, b }) => {
+            Some(color) => {
                 gr.gfxHasAlpha = true;
-                gr.gfxAlphaColor = tagRGBQUAD {
-                    rgbBlue: b,
-                    rgbGreen: g,
-                    rgbRed: r,
-                    rgbReserved: 0,
-                };
+                gr.gfxAlphaColor = rgb_quad(color);
             }
-            Transparency::Color(Color::GBR16(clr)) => {
-                gr.gfxHasAlpha = true;
+        }
 
-                // 5 bit per color with one bit left to spare
-                // Each channel goes from 0 to 31 (0b11111) and we want to map that to 0 to 255
-                // We can do this by multiplying by 255 and dividing by 31
-                let r = (clr & 0b11111) * 0b1111_1111 / 0b11111;
-                let g = ((clr >> 5) & 0b11111) * 0b1111_1111 / 0b11111;
-                let b = ((clr >> 10) & 0b11111) * 0b1111_1111 / 0b11111;
-
-                gr.gfxAlphaColor = tagRGBQUAD {
-                    rgbBlue: b as u8,
-                    rgbGreen: g as u8,
-                    rgbRed: r as u8,
-                    rgbReserved: 0,
-                };
+        let gfx_compression = self.gfx_compression.or(self.compression);
+        let palette_compression = self.palette_compression.or(self.compression);
+        let map_compression = self.map_compression.or(self.compression);
+
+        if let Some(compression) = gfx_compression {
+            gr.gfxCompression = compression_value(compression);
+        }
+        if let Some(compression) = palette_compression {
+            gr.palCompression = compression_value(compression);
+        }
+        if let Some(compression) = map_compression {
+            gr.mapCompression = compression_value(compression);
+        }
+
+        let gfx_data_type = self.gfx_data_type.or(self.data_type).unwrap_or_default();
+        let palette_data_type = self
+            .palette_data_type
+            .or(self.data_type)
+            .unwrap_or_default();
+        let map_data_type = self.map_data_type.or(self.data_type).unwrap_or_default();
+
+        gr.gfxDataType = data_type_value(gfx_data_type);
+        gr.palDataType = data_type_value(palette_data_type);
+        gr.mapDataType = data_type_value(map_data_type);
+
+        // Flip and palette reduction only apply on top of tile reduction, so without it nothing
+        // is reduced and the spec says so. The map is still exported, like grit does.
+        let requested = self.map_reduction.unwrap_or_default();
+        let map_reduction = match requested.tiles {
+            true => requested,
+            false => MapReduction::NONE,
+        };
+        if !requested.is_none() {
+            let mut redux = EGritMapReduction_GRIT_RDX_OFF;
+            if map_reduction.tiles {
+                redux |= EGritMapReduction_GRIT_RDX_TILE;
+            }
+            if map_reduction.flips {
+                redux |= EGritMapReduction_GRIT_RDX_FLIP;
+            }
+            if map_reduction.palettes {
+                redux |= EGritMapReduction_GRIT_RDX_PBANK;
             }
+
+            // Reducing the map implies exporting one, just like `-mR` does in grit
+            gr.mapProcMode = EGritProcMode_GRIT_EXPORT as u8;
+            gr.mapRedux = redux as u8;
         }
 
-        if let Some(compression) = &self.compression {
-            let value = match compression {
-                Compression::Off => EGritCompression_GRIT_CPRS_OFF as u8,
-                Compression::LZ77 => EGritCompression_GRIT_CPRS_LZ77 as u8,
-                Compression::Huffman => EGritCompression_GRIT_CPRS_HUFF as u8,
-                Compression::RLE => EGritCompression_GRIT_CPRS_RLE as u8,
-                Compression::OffHeader => EGritCompression_GRIT_CPRS_HEADER as u8,
+        if let Some(map_layout) = &self.map_layout {
+            gr.mapProcMode = EGritProcMode_GRIT_EXPORT as u8;
+            gr.mapLayout = match map_layout {
+                MapLayout::Flat => EGritMapLayout_GRIT_MAP_FLAT as u8,
+                MapLayout::ScreenBlock => EGritMapLayout_GRIT_MAP_REG as u8,
+                MapLayout::Affine => EGritMapLayout_GRIT_MAP_AFFINE as u8,
             };
+        }
+
+        if let Some(format) = &self.map_entry_format {
+            // grit keeps the masks unshifted
+            let field = |field: MapField| ((1u64 << field.len) - 1) as u32;
+
+            gr.mapProcMode = EGritProcMode_GRIT_EXPORT as u8;
+            gr.mapEntrySize = format.bits;
+            gr.msFormat.indexMask = field(format.index);
+            gr.msFormat.indexShift = format.index.shift as u32;
+            gr.msFormat.hflipMask = field(format.hflip);
+            gr.msFormat.hflipShift = format.hflip.shift as u32;
+            gr.msFormat.vflipMask = field(format.vflip);
+            gr.msFormat.vflipShift = format.vflip.shift as u32;
+            gr.msFormat.pbankMask = field(format.palette);
+            gr.msFormat.pbankShift = format.palette.shift as u32;
+        }
 
-            gr.gfxCompression = value;
-            gr.palCompression = value;
-            gr.mapCompression = value;
+        for (export, mode) in [
+            (self.gfx, &mut gr.gfxProcMode),
+            (self.palette, &mut gr.palProcMode),
+            (self.map, &mut gr.mapProcMode),
+        ] {
+            match export {
+                Some(true) => *mode = EGritProcMode_GRIT_EXPORT as u8,
+                Some(false) => *mode = EGritProcMode_GRIT_EXCLUDE as u8,
+                None => {}
+            }
+        }
+
+        if let Some(offset) = self.gfx_offset {
+            gr.gfxOffset = offset;
+        }
+        if let Some(offset) = self.map_offset {
+            gr.msFormat.base = offset;
         }
 
-        let mut symbol_name = unsafe { std::mem::zeroed::<[std::ffi::c_char; 256]>() };
         gr.bExport = false;
-        gr.symName = symbol_name.as_mut_ptr();
 
         // This actually runs the conversion
-        let result = unsafe { grit_run(gr) };
+        let result = grit.run();
 
         // Read all the records
-        let gfx = gr._gfxRec.read();
-        let palette = gr._palRec.read();
-        let map = gr._mapRec.read();
-        let meta = gr._metaRec.read();
-
-        // [`grit_free`] frees the memory allocated by [`grit_alloc`] and a bunch of nested pointers
-        // If we set those pointers to null, free() will not do anything
-        gr.srcDib = std::ptr::null_mut();
-        gr.srcPath = std::ptr::null_mut();
-        gr.symName = std::ptr::null_mut();
-
-        unsafe { grit_free(gr as *mut _) };
-
-        if result {
-            Ok(Bitmap {
-                gfx,
-                palette,
-                map,
-                meta,
-                spec: BitmapSpec {
-                    bit_depth: self.bit_depth_override,
-                    format: self.format.unwrap_or_default(),
-                    transparency: self.transparency.unwrap_or_default(),
-                },
+        let gfx = grit._gfxRec.read();
+        let palette = grit._palRec.read();
+        let map = grit._mapRec.read();
+        let meta = grit._metaRec.read();
+
+        // grit has filled in the defaults by now
+        let (source_width, source_height) =
+            unsafe { (dib_get_width(grit.srcDib), dib_get_height(grit.srcDib)) };
+        let mut spec = BitmapSpec {
+            bit_depth: Some(
+                self.bit_depth_override
+                    .unwrap_or(BitDepth::Custom(grit.gfxBpp)),
+            ),
+            format: self.format.unwrap_or_default(),
+            transparency: transparent.map_or(Transparency::Disabled, Transparency::Color),
+            palette_range,
+            map_reduction,
+            map_layout: self.map_layout.unwrap_or_default(),
+            map_entry_format: match (self.map_entry_format, self.map_layout) {
+                (Some(format), _) => format,
+                (None, Some(MapLayout::Affine)) => MapEntryFormat::AFFINE,
+                (None, _) => MapEntryFormat::SCREEN_ENTRY,
+            },
+            gfx_compression: compression_from_value(grit.gfxCompression),
+            palette_compression: compression_from_value(grit.palCompression),
+            map_compression: compression_from_value(grit.mapCompression),
+            gfx_data_type,
+            palette_data_type,
+            map_data_type,
+            sections: Sections {
+                gfx: grit.gfxProcMode != EGritProcMode_GRIT_EXCLUDE as u8,
+                palette: grit.palProcMode != EGritProcMode_GRIT_EXCLUDE as u8,
+                map: grit.mapProcMode != EGritProcMode_GRIT_EXCLUDE as u8,
+            },
+            gfx_offset: grit.gfxOffset,
+            map_offset: grit.msFormat.base,
+            texture: None,
+            source_width: source_width as u32,
+            source_height: source_height as u32,
+            width: (grit.areaRight - grit.areaLeft) as u32,
+            height: (grit.areaBottom - grit.areaTop) as u32,
+            bpp: grit.gfxBpp,
+            tile_width: grit.tileWidth,
+            tile_height: grit.tileHeight,
+            meta_width: grit.metaWidth,
+            meta_height: grit.metaHeight,
+            tile_count: 0,
+            map_count: 0,
+            palette_count: 0,
+        };
+
+        // Frees the record and the source image
+        drop(grit);
+
+        let warnings = match result {
+            Ok(warnings) => warnings,
+            Err(messages) => {
+                return Err(match messages.is_empty() {
+                    true => Error::ConversionError(self.source.name()),
+                    false => Error::Grit {
+                        input: self.source.name(),
+                        messages,
+                    },
+                });
+            }
+        };
+
+        // Counted before padding, which would add entries that aren't there
+        let tile_bits = spec.tile_width as usize * spec.tile_height as usize * spec.bpp as usize;
+        spec.tile_count = (raw_size(&gfx, spec.gfx_compression) * 8)
+            .checked_div(tile_bits)
+            .unwrap_or(0) as u32;
+        spec.map_count =
+            (raw_size(&map, spec.map_compression) / spec.map_entry_format.size()) as u32;
+        spec.palette_count = (raw_size(&palette, spec.palette_compression) / 2) as u16;
+
+        if self.is_texture() {
+            let format = match (self.bit_depth_override, spec.bpp) {
+                (Some(BitDepth::A3I5), _) => TextureFormat::A3I5,
+                (Some(BitDepth::A5I3), _) => TextureFormat::A5I3,
+                (Some(BitDepth::FourByFour), _) => TextureFormat::FourByFour,
+                (_, 2) => TextureFormat::Palette4,
+                (_, 4) => TextureFormat::Palette16,
+                (_, 8) => TextureFormat::Palette256,
+                (_, 16) => TextureFormat::Direct,
+                (_, bpp) => return Err(Error::BadBitDepth(bpp)),
+            };
+
+            spec.texture = Some(TextureSpec {
+                format,
+                width: spec.width as u16,
+                height: spec.height as u16,
+                transparent: format.is_paletted() && transparent.is_some(),
+            });
+        }
+
+        Ok(Bitmap {
+            gfx: pad(gfx, gfx_data_type),
+            palette: pad(palette, palette_data_type),
+            map: pad(map, map_data_type),
+            meta: pad(meta, map_data_type),
+            spec,
+            warnings,
+        })
+    }
+}
+
+impl BatchBuilder {
+    pub fn new<I, S>(inputs: I) -> BatchBuilder
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        BatchBuilder {
+            inputs: inputs.into_iter().map(|i| i.as_ref().to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_input(mut self, input: impl AsRef<str>) -> Self {
+        self.inputs.push(input.as_ref().to_string());
+        self
+    }
+
+    pub fn with_settings(mut self, settings: BitmapBuilder) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    pub fn build(&self) -> Result<Batch> {
+        let images = self
+            .inputs
+            .iter()
+            .map(|input| load_rgba(input))
+            .collect::<Result<Vec<_>>>()?;
+
+        // Every image has to agree on the transparent color, so the modes that read it from the
+        // source go by the first image
+        let transparency = match self.settings.transparency.unwrap_or_default() {
+            transparency @ (Transparency::FirstPaletteEntry | Transparency::TopLeftPixel) => {
+                match self.inputs.first() {
+                    Some(input) => Transparency::Color(load_color(input, transparency)?),
+                    None => Transparency::Disabled,
+                }
+            }
+            transparency => transparency,
+        };
+
+        // The transparent color always takes index 0, like grit does for a single image. Colors
+        // are compared as they end up in palette RAM, so ones grit would merge share an entry.
+        let mut colors = Vec::<Bgr555>::new();
+        if let Transparency::Color(color) = transparency {
+            colors.push(color.to_bgr555().with_alpha(false));
+        }
+
+        let indexed = images
+            .iter()
+            .map(|(_, _, data)| {
+                data.chunks_exact(4)
+                    .map(|px| {
+                        let color = Bgr555::from_rgb888(px[0], px[1], px[2]);
+                        let index = colors.iter().position(|c| *c == color).unwrap_or_else(|| {
+                            colors.push(color);
+                            colors.len() - 1
+                        });
+                        index.min(255) as u8
+                    })
+                    .collect::<Vec<_>>()
             })
-        } else {
-            Err(Error::ConversionError(self.input.clone()))
+            .collect::<Vec<_>>();
+
+        if colors.len() > 256 {
+            return Err(Error::BadPalette(colors.len()));
+        }
+
+        let palette = colors.into_iter().map(Color::Bgr555).collect::<Vec<_>>();
+
+        let mut bitmaps = images
+            .iter()
+            .zip(indexed)
+            .map(|((width, height, _), data)| {
+                BitmapBuilder {
+                    source: Source::Indexed {
+                        width: *width,
+                        height: *height,
+                        data,
+                        palette: palette.clone(),
+                    },
+                    transparency: Some(transparency),
+                    ..self.settings.clone()
+                }
+                .build()
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // Every image was converted with the same palette, so keep a single copy of it
+        let palette = bitmaps
+            .first_mut()
+            .map(|bitmap| std::mem::take(&mut bitmap.palette))
+            .unwrap_or_default();
+        for bitmap in bitmaps.iter_mut().skip(1) {
+            bitmap.palette.clear();
         }
+
+        Ok(Batch { palette, bitmaps })
     }
 }
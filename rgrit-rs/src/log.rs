@@ -0,0 +1,102 @@
+//! Captures what libgrit prints while it converts.
+//!
+//! grit reports problems like too many colors or a bad area by printing them, so the output of a
+//! conversion is redirected into a pipe and turned into error messages, or into warnings if the
+//! conversion succeeds.
+
+/// Runs `f` with stdout and stderr redirected and returns everything that was written to them.
+///
+/// The redirection is process wide. Rust's stdout and stderr stay locked meanwhile, so other
+/// threads that print through them wait for the conversion instead of ending up in the log.
+///
+/// Nothing is captured inside a proc macro like `grit!`, where the descriptors belong to rustc
+/// and carry its diagnostics.
+#[cfg(unix)]
+pub(crate) fn capture<T>(f: impl FnOnce() -> T) -> (T, String) {
+    use std::io::{Read, Write};
+    use std::os::fd::AsRawFd;
+
+    if proc_macro::is_available() {
+        return (f(), String::new());
+    }
+
+    let Ok((mut reader, writer)) = std::io::pipe() else {
+        return (f(), String::new());
+    };
+
+    // Drain the pipe while grit writes, it would block once the pipe buffer is full otherwise
+    let log = std::thread::spawn(move || {
+        let mut log = Vec::new();
+        let _ = reader.read_to_end(&mut log);
+        String::from_utf8_lossy(&log).into_owned()
+    });
+
+    let mut stdout = std::io::stdout().lock();
+    let stderr = std::io::stderr().lock();
+    let _ = stdout.flush();
+
+    let redirect = Redirect::new(writer.as_raw_fd());
+    drop(writer);
+    let result = f();
+    // Restoring the descriptors closes the last write end, which ends the reader
+    drop(redirect);
+
+    drop((stdout, stderr));
+
+    (result, log.join().unwrap_or_default())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn capture<T>(f: impl FnOnce() -> T) -> (T, String) {
+    (f(), String::new())
+}
+
+/// Points stdout and stderr at another descriptor until it's dropped, which also happens when the
+/// conversion panics.
+#[cfg(unix)]
+struct Redirect {
+    /// Duplicates of the original descriptors, negative if `dup` failed.
+    saved: [libc::c_int; 2],
+}
+
+#[cfg(unix)]
+impl Redirect {
+    const FDS: [libc::c_int; 2] = [libc::STDOUT_FILENO, libc::STDERR_FILENO];
+
+    fn new(to: libc::c_int) -> Redirect {
+        let saved = Redirect::FDS.map(|fd| unsafe {
+            libc::fflush(std::ptr::null_mut());
+            let saved = libc::dup(fd);
+            if saved >= 0 {
+                libc::dup2(to, fd);
+            }
+            saved
+        });
+
+        Redirect { saved }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Redirect {
+    fn drop(&mut self) {
+        unsafe { libc::fflush(std::ptr::null_mut()) };
+        for (fd, saved) in Redirect::FDS.into_iter().zip(self.saved) {
+            if saved >= 0 {
+                unsafe {
+                    libc::dup2(saved, fd);
+                    libc::close(saved);
+                }
+            }
+        }
+    }
+}
+
+/// Splits a captured log into its non-empty lines.
+pub(crate) fn messages(log: &str) -> Vec<String> {
+    log.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
@@ -0,0 +1,208 @@
+//! Slicing converted sprite sheets into frames, see [`SpriteSheetBuilder`].
+
+use crate::{Bitmap, Error, Result, Setting};
+use rgrit_core::{BitmapSpec, Compression, Frame, GfxFormat, ObjMapping};
+
+/// The sizes an OBJ can have in pixels, from OBJ attributes 0 and 1.
+const OBJ_SIZES: [(u32, u32); 12] = [
+    (8, 8),
+    (16, 16),
+    (32, 32),
+    (64, 64),
+    (16, 8),
+    (32, 8),
+    (32, 16),
+    (64, 32),
+    (8, 16),
+    (8, 32),
+    (16, 32),
+    (32, 64),
+];
+
+/// Slices a tiled [`Bitmap`] into frames and lays their tiles out for an [`ObjMapping`].
+///
+/// Frames are read from the sheet left to right, top to bottom. Without a frame size the whole
+/// height or width of the sheet is one frame, e.g. `with_frame_height(32)` alone cuts a
+/// vertical strip into 32 pixel high frames.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SpriteSheetBuilder {
+    frame_width: Option<u32>,
+    frame_height: Option<u32>,
+    frame_count: Option<u32>,
+    mapping: Option<ObjMapping>,
+}
+
+/// A sprite sheet sliced into frames.
+#[derive(Clone, Debug)]
+pub struct SpriteSheet {
+    /// All frames, ready to be copied to OBJ VRAM.
+    pub tiles: Vec<u8>,
+    pub palette: Vec<u8>,
+    pub frames: Vec<Frame>,
+    pub mapping: ObjMapping,
+    /// The settings the whole sheet was converted with.
+    pub spec: BitmapSpec,
+}
+
+impl SpriteSheet {
+    /// The tiles of frame `index`, `None` if there is no such frame or the sheet uses 2D
+    /// mapping, where frames aren't contiguous.
+    pub fn frame(&self, index: usize) -> Option<&[u8]> {
+        let frame = self.frames.get(index)?;
+        match self.mapping {
+            ObjMapping::OneD => self.tiles.get(frame.range(self.spec.bpp)),
+            ObjMapping::TwoD => None,
+        }
+    }
+}
+
+impl SpriteSheetBuilder {
+    pub fn new() -> SpriteSheetBuilder {
+        SpriteSheetBuilder::default()
+    }
+
+    /// Width of a frame in pixels, together with the height one of the OBJ sizes from 8x8 to
+    /// 64x64.
+    pub fn with_frame_width(mut self, frame_width: u32) -> Self {
+        self.frame_width = Some(frame_width);
+        self
+    }
+
+    /// Height of a frame in pixels, see [`SpriteSheetBuilder::with_frame_width`].
+    pub fn with_frame_height(mut self, frame_height: u32) -> Self {
+        self.frame_height = Some(frame_height);
+        self
+    }
+
+    /// Only keeps the first `frame_count` frames, e.g. when the last row of the sheet isn't full.
+    pub fn with_frame_count(mut self, frame_count: u32) -> Self {
+        self.frame_count = Some(frame_count);
+        self
+    }
+
+    pub fn with_mapping(mut self, mapping: ObjMapping) -> Self {
+        self.mapping = Some(mapping);
+        self
+    }
+
+    /// Slices `bitmap`, which has to be 4 or 8bpp graphics in uncompressed 8x8 tiles without a
+    /// map or metatiles.
+    pub fn build(&self, bitmap: &Bitmap) -> Result<SpriteSheet> {
+        let spec = &bitmap.spec;
+        let reason = if !matches!(spec.format, GfxFormat::Tile) {
+            Some("the graphics aren't tiled")
+        } else if (spec.tile_width, spec.tile_height) != (8, 8) {
+            Some("OBJ tiles are 8x8 pixels")
+        } else if (spec.meta_width, spec.meta_height) != (1, 1) {
+            Some("metatiles reorder the tiles")
+        } else if !bitmap.map.is_empty() {
+            Some("the tiles are reduced into a map")
+        } else if spec.gfx_compression != Compression::Off {
+            Some("the graphics are compressed")
+        } else if !matches!(spec.bpp, 4 | 8) {
+            Some("OBJ tiles are 4 or 8bpp")
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            return Err(Error::NotSpriteSheet(reason));
+        }
+
+        let frame_width = self.frame_width.unwrap_or(spec.width);
+        let frame_height = self.frame_height.unwrap_or(spec.height);
+        for (setting, size, image) in [
+            (Setting::FrameWidth, frame_width, spec.width),
+            (Setting::FrameHeight, frame_height, spec.height),
+        ] {
+            if size == 0 || size % 8 != 0 || size > image {
+                return Err(Error::BadFrameSize {
+                    setting,
+                    size,
+                    image,
+                });
+            }
+        }
+        if !OBJ_SIZES.contains(&(frame_width, frame_height)) {
+            return Err(Error::BadFrameShape {
+                width: frame_width,
+                height: frame_height,
+            });
+        }
+
+        let columns = spec.width / frame_width;
+        let frames = columns * (spec.height / frame_height);
+        let frame_count = self.frame_count.unwrap_or(frames);
+        if frame_count == 0 || frame_count > frames {
+            return Err(Error::BadFrameCount {
+                count: frame_count,
+                frames,
+            });
+        }
+
+        let mapping = self.mapping.unwrap_or_default();
+        let tile_size = spec.bpp as usize * 8;
+        let row_tiles = ObjMapping::ROW_SIZE / tile_size;
+        // At most 8 tiles, so a frame always fits into a 2D mapping row
+        let (width, height) = ((frame_width / 8) as usize, (frame_height / 8) as usize);
+
+        // grit writes the tiles of the sheet row by row, `sheet_tiles` to a row
+        let sheet_tiles = spec.width as usize / 8;
+        let source = |x: usize, y: usize| {
+            let start = (y * sheet_tiles + x) * tile_size;
+            bitmap.gfx.get(start..start + tile_size)
+        };
+
+        // With 2D mapping the frames are packed into shelves as high as one frame
+        let shelf_frames = row_tiles / width;
+        let mut tiles = match mapping {
+            ObjMapping::OneD => Vec::new(),
+            ObjMapping::TwoD => {
+                let shelves = (frame_count as usize).div_ceil(shelf_frames);
+                vec![0; shelves * height * ObjMapping::ROW_SIZE]
+            }
+        };
+        let mut sliced = Vec::with_capacity(frame_count as usize);
+
+        for i in 0..frame_count as usize {
+            let (column, row) = (i % columns as usize, i / columns as usize);
+            let start = match mapping {
+                ObjMapping::OneD => tiles.len(),
+                ObjMapping::TwoD => {
+                    let (shelf, slot) = (i / shelf_frames, i % shelf_frames);
+                    shelf * height * ObjMapping::ROW_SIZE + slot * width * tile_size
+                }
+            };
+
+            for y in 0..height {
+                for x in 0..width {
+                    let tile = source(column * width + x, row * height + y).ok_or(
+                        Error::NotSpriteSheet("the graphics are smaller than the sheet"),
+                    )?;
+                    match mapping {
+                        ObjMapping::OneD => tiles.extend_from_slice(tile),
+                        ObjMapping::TwoD => {
+                            let offset = start + y * ObjMapping::ROW_SIZE + x * tile_size;
+                            tiles[offset..offset + tile_size].copy_from_slice(tile);
+                        }
+                    }
+                }
+            }
+
+            sliced.push(Frame {
+                tile: u16::try_from(start / Frame::TILE_UNIT).map_err(|_| {
+                    Error::NotSpriteSheet("the frames need more tile indices than fit into a u16")
+                })?,
+                width: width as u8,
+                height: height as u8,
+            });
+        }
+
+        Ok(SpriteSheet {
+            tiles,
+            palette: bitmap.palette.clone(),
+            frames: sliced,
+            mapping,
+            spec: *spec,
+        })
+    }
+}
@@ -0,0 +1,358 @@
+//! Checks [`BitmapBuilder`] settings before anything is handed to grit.
+
+use crate::{BitmapBuilder, Error, Result, Source};
+use rgrit_core::{BitDepth, GfxFormat, MapLayout, Transparency};
+use std::fmt::{Display, Formatter};
+
+/// A [`BitmapBuilder`] or [`SpriteSheetBuilder`](crate::SpriteSheetBuilder) setting, named like
+/// the key of the `grit!` macro.
+///
+/// Validation errors name the settings they are about, see [`Error::settings`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Setting {
+    Format,
+    BitDepth,
+    Transparency,
+    TileWidth,
+    TileHeight,
+    MetaWidth,
+    MetaHeight,
+    AreaLeft,
+    AreaRight,
+    AreaWidth,
+    AreaTop,
+    AreaBottom,
+    AreaHeight,
+    PaletteStart,
+    PaletteEnd,
+    PaletteCount,
+    Texture,
+    MapLayout,
+    MapEntryFormat,
+    MapReduction,
+    MapOffset,
+    Gfx,
+    Palette,
+    Map,
+    FrameWidth,
+    FrameHeight,
+    FrameCount,
+    ObjMapping,
+}
+
+impl Setting {
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Setting::Format => "format",
+            Setting::BitDepth => "bit_depth",
+            Setting::Transparency => "transparency",
+            Setting::TileWidth => "tile_width",
+            Setting::TileHeight => "tile_height",
+            Setting::MetaWidth => "meta_width",
+            Setting::MetaHeight => "meta_height",
+            Setting::AreaLeft => "area_left",
+            Setting::AreaRight => "area_right",
+            Setting::AreaWidth => "area_width",
+            Setting::AreaTop => "area_top",
+            Setting::AreaBottom => "area_bottom",
+            Setting::AreaHeight => "area_height",
+            Setting::PaletteStart => "palette_start",
+            Setting::PaletteEnd => "palette_end",
+            Setting::PaletteCount => "palette_count",
+            Setting::Texture => "texture",
+            Setting::MapLayout => "map_layout",
+            Setting::MapEntryFormat => "map_entry_format",
+            Setting::MapReduction => "map_reduction",
+            Setting::MapOffset => "map_offset",
+            Setting::Gfx => "gfx",
+            Setting::Palette => "palette",
+            Setting::Map => "map",
+            Setting::FrameWidth => "frame_width",
+            Setting::FrameHeight => "frame_height",
+            Setting::FrameCount => "frame_count",
+            Setting::ObjMapping => "obj_mapping",
+        }
+    }
+}
+
+impl Display for Setting {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl BitmapBuilder {
+    /// Checks the settings for values grit would reject or quietly mishandle.
+    ///
+    /// Checks that need the size of the image only run here for in-memory sources, for files
+    /// they run in [`BitmapBuilder::build`] once the image is loaded.
+    pub fn validate(&self) -> Result<()> {
+        let format = self.format.unwrap_or_default();
+
+        match self.bit_depth_override {
+            Some(BitDepth::Custom(n)) if !matches!(n, 1 | 2 | 4 | 8 | 16) => {
+                return Err(Error::BadBitDepth(n));
+            }
+            Some(BitDepth::A3I5 | BitDepth::A5I3 | BitDepth::FourByFour)
+                if matches!(format, GfxFormat::Tile) =>
+            {
+                return Err(Error::Conflict {
+                    setting: Setting::BitDepth,
+                    other: Setting::Format,
+                    reason: "NDS texture formats can't be tiled",
+                });
+            }
+            Some(BitDepth::Custom(1)) if self.texture == Some(true) => {
+                return Err(Error::Conflict {
+                    setting: Setting::BitDepth,
+                    other: Setting::Texture,
+                    reason: "NDS textures have no 1bpp format",
+                });
+            }
+            _ => {}
+        }
+
+        if let (Some(true), GfxFormat::Tile) = (self.texture, format) {
+            return Err(Error::Conflict {
+                setting: Setting::Texture,
+                other: Setting::Format,
+                reason: "NDS textures can't be tiled",
+            });
+        }
+
+        // Disabling transparency switches grit to the NDS alpha bitmap mode
+        if let (Some(Transparency::Disabled), GfxFormat::Tile) = (self.transparency, format) {
+            return Err(Error::Conflict {
+                setting: Setting::Transparency,
+                other: Setting::Format,
+                reason: "disabling transparency selects the NDS alpha bitmap mode",
+            });
+        }
+
+        if let GfxFormat::Tile = format {
+            for (setting, size) in [
+                (Setting::TileWidth, self.tile_width),
+                (Setting::TileHeight, self.tile_height),
+            ] {
+                match size {
+                    Some(size) if size == 0 || !size.is_multiple_of(8) => {
+                        return Err(Error::BadTileSize { setting, size });
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if let Some(entry_format) = &self.map_entry_format {
+            entry_format.validate().map_err(Error::BadMapEntryFormat)?;
+
+            if let Some(MapLayout::Affine) = self.map_layout {
+                return Err(Error::Conflict {
+                    setting: Setting::MapEntryFormat,
+                    other: Setting::MapLayout,
+                    reason: "the affine layout has its own 8-bit entries",
+                });
+            }
+        }
+
+        if self.map == Some(false) {
+            let map_settings = [
+                (Setting::MapLayout, self.map_layout.is_some()),
+                (
+                    Setting::MapReduction,
+                    self.map_reduction
+                        .is_some_and(|reduction| !reduction.is_none()),
+                ),
+                (Setting::MapEntryFormat, self.map_entry_format.is_some()),
+                (Setting::MapOffset, self.map_offset.is_some()),
+            ];
+            if let Some((other, _)) = map_settings.into_iter().find(|(_, set)| *set) {
+                return Err(Error::Conflict {
+                    setting: Setting::Map,
+                    other,
+                    reason: "the map is excluded",
+                });
+            }
+        }
+
+        for (setting, size) in [
+            (Setting::MetaWidth, self.meta_width),
+            (Setting::MetaHeight, self.meta_height),
+        ] {
+            if size == Some(0) {
+                return Err(Error::ZeroMetaSize(setting));
+            }
+        }
+
+        let start = self.palette_start.unwrap_or(0);
+        if start >= 256 {
+            return Err(Error::BadPaletteRange {
+                setting: Setting::PaletteStart,
+                value: start,
+            });
+        }
+        match (self.palette_end, self.palette_count) {
+            (Some(end), _) if end > 256 => {
+                return Err(Error::BadPaletteRange {
+                    setting: Setting::PaletteEnd,
+                    value: end,
+                });
+            }
+            (Some(end), _) if end <= start => {
+                return Err(Error::Conflict {
+                    setting: Setting::PaletteEnd,
+                    other: Setting::PaletteStart,
+                    reason: "the palette range ends before it starts",
+                });
+            }
+            (None, Some(count)) if start as u32 + count as u32 > 256 => {
+                return Err(Error::BadPaletteRange {
+                    setting: Setting::PaletteCount,
+                    value: count,
+                });
+            }
+            _ => {}
+        }
+
+        match &self.source {
+            Source::Path(_) => Ok(()),
+            Source::Rgba { width, height, .. } | Source::Indexed { width, height, .. } => {
+                self.validate_size(*width, *height)
+            }
+        }
+    }
+
+    /// Checks the area and metatile settings against the size of the image.
+    pub(crate) fn validate_size(&self, width: u32, height: u32) -> Result<()> {
+        let (left, top) = (self.area_left.unwrap_or(0), self.area_top.unwrap_or(0));
+        let (right, bottom) = self.area_edges();
+
+        for (setting, start, end, size) in [
+            (Setting::AreaLeft, left, right, width),
+            (Setting::AreaTop, top, bottom, height),
+        ] {
+            if start < 0 || start >= size as i32 {
+                return Err(Error::AreaOutOfBounds {
+                    setting,
+                    value: start,
+                    size,
+                });
+            }
+
+            if let Some(Edge {
+                setting,
+                value,
+                end,
+            }) = end
+            {
+                if end <= start || end > size as i32 {
+                    return Err(Error::AreaOutOfBounds {
+                        setting,
+                        value,
+                        size,
+                    });
+                }
+            }
+        }
+
+        let right = right.map_or(width as i32, |right| right.end);
+        let bottom = bottom.map_or(height as i32, |bottom| bottom.end);
+
+        if self.is_texture() {
+            for (setting, edge, size) in [
+                (Setting::AreaWidth, self.area_edges().0, right - left),
+                (Setting::AreaHeight, self.area_edges().1, bottom - top),
+            ] {
+                if !(8..=1024).contains(&size) || !(size as u32).is_power_of_two() {
+                    return Err(Error::BadTextureSize {
+                        setting: edge.map_or(setting, |edge| edge.setting),
+                        size: size as u32,
+                    });
+                }
+            }
+        }
+
+        if let GfxFormat::Tile = self.format.unwrap_or_default() {
+            for (setting, meta, tile, pixels) in [
+                (
+                    Setting::MetaWidth,
+                    self.meta_width,
+                    self.tile_width,
+                    right - left,
+                ),
+                (
+                    Setting::MetaHeight,
+                    self.meta_height,
+                    self.tile_height,
+                    bottom - top,
+                ),
+            ] {
+                let tiles = (pixels as u32).div_ceil(tile.unwrap_or(8) as u32);
+                match meta {
+                    Some(size) if size > 1 && !tiles.is_multiple_of(size as u32) => {
+                        return Err(Error::BadMetaSize {
+                            setting,
+                            size,
+                            tiles,
+                        });
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether the bitmap is converted to an NDS texture, either explicitly or through its format.
+    pub(crate) fn is_texture(&self) -> bool {
+        self.texture.unwrap_or(false)
+            || matches!(
+                self.bit_depth_override,
+                Some(BitDepth::A3I5 | BitDepth::A5I3 | BitDepth::FourByFour)
+            )
+    }
+
+    /// Resolves the right and bottom edge of the area, a width or height wins over an edge.
+    ///
+    /// `None` leaves the edge to grit.
+    pub(crate) fn area_edges(&self) -> (Option<Edge>, Option<Edge>) {
+        let edge = |start: Option<i32>, size, end| match (size, end) {
+            ((setting, Some(value)), _) if value != -1 => Some(Edge {
+                setting,
+                value,
+                // Saturates so a huge width ends up out of bounds instead of wrapping
+                end: start.unwrap_or(0).saturating_add(value),
+            }),
+            (_, (setting, Some(value))) if value != -1 => Some(Edge {
+                setting,
+                value,
+                end: value,
+            }),
+            _ => None,
+        };
+
+        (
+            edge(
+                self.area_left,
+                (Setting::AreaWidth, self.area_width),
+                (Setting::AreaRight, self.area_right),
+            ),
+            edge(
+                self.area_top,
+                (Setting::AreaHeight, self.area_height),
+                (Setting::AreaBottom, self.area_bottom),
+            ),
+        )
+    }
+}
+
+/// The right or bottom edge of the area.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Edge {
+    /// The setting the edge came from.
+    setting: Setting,
+    /// The value of that setting.
+    value: i32,
+    pub(crate) end: i32,
+}
@@ -0,0 +1,36 @@
+use rgrit_core::Color;
+use rgrit_rs::{BitmapBuilder, Error};
+
+#[test]
+fn empty_images() {
+    let palette = [Color::RGB { r: 0, g: 0, b: 0 }];
+    for (width, height) in [(0, 0), (0, 8), (8, 0)] {
+        assert!(matches!(
+            BitmapBuilder::from_rgba(width, height, &[]),
+            Err(Error::EmptyImage { .. })
+        ));
+        assert!(matches!(
+            BitmapBuilder::from_indexed(width, height, &[], &palette),
+            Err(Error::EmptyImage { .. })
+        ));
+    }
+}
+
+#[test]
+fn buffer_sizes() {
+    assert!(matches!(
+        BitmapBuilder::from_rgba(2, 2, &[0; 15]),
+        Err(Error::BadBufferSize {
+            expected: 16,
+            actual: 15
+        })
+    ));
+    assert!(matches!(
+        BitmapBuilder::from_indexed(2, 2, &[0; 5], &[]),
+        Err(Error::BadBufferSize {
+            expected: 4,
+            actual: 5
+        })
+    ));
+    BitmapBuilder::from_rgba(2, 2, &[0; 16]).unwrap();
+}
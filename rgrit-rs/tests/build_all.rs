@@ -0,0 +1,56 @@
+use rgrit_core::{BitDepth, Color, GfxFormat, MapLayout, MapReduction};
+use rgrit_rs::{build_all, Bitmap, BitmapBuilder};
+
+fn builder(seed: u8) -> BitmapBuilder {
+    let palette = (0..16)
+        .map(|i| Color::RGB {
+            r: i * 16,
+            g: seed.wrapping_mul(i),
+            b: 255 - i * 16,
+        })
+        .collect::<Vec<_>>();
+    let pixels = (0..32 * 16)
+        .map(|i| (i as u8 ^ seed).wrapping_mul(seed | 1) % 16)
+        .collect::<Vec<_>>();
+
+    BitmapBuilder::from_indexed(32, 16, &pixels, &palette)
+        .unwrap()
+        .with_format(GfxFormat::Tile)
+        .with_bit_depth_override(BitDepth::Custom(4))
+        .with_map_layout(MapLayout::Flat)
+        .with_map_reduction(MapReduction::TILES_FLIPS)
+}
+
+fn assert_same(left: &Bitmap, right: &Bitmap) {
+    assert_eq!(left.gfx, right.gfx);
+    assert_eq!(left.palette, right.palette);
+    assert_eq!(left.map, right.map);
+    assert_eq!(left.meta, right.meta);
+    assert_eq!(left.warnings, right.warnings);
+    assert_eq!(format!("{:?}", left.spec), format!("{:?}", right.spec));
+}
+
+#[test]
+fn matches_single_builds() {
+    let builders = (0..8).map(builder).collect::<Vec<_>>();
+    let results = build_all(&builders);
+
+    assert_eq!(results.len(), builders.len());
+    for (builder, result) in builders.iter().zip(results) {
+        assert_same(&result.unwrap(), &builder.build().unwrap());
+    }
+}
+
+#[test]
+fn keeps_failures_separate() {
+    let builders = [
+        builder(1),
+        builder(2).with_format(GfxFormat::Tile).with_tile_width(3),
+        builder(3),
+    ];
+    let results = build_all(&builders);
+
+    assert!(results[0].is_ok());
+    assert!(results[1].is_err());
+    assert_same(results[2].as_ref().unwrap(), &builders[2].build().unwrap());
+}
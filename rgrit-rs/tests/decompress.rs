@@ -0,0 +1,59 @@
+use proptest::prelude::*;
+use rgrit_core::decompress::decompress;
+use rgrit_core::{BitDepth, Color, Compression, GfxFormat, MapLayout};
+use rgrit_rs::{Bitmap, BitmapBuilder};
+
+fn image() -> impl Strategy<Value = (u32, u32, Vec<u8>, Vec<Color>)> {
+    (1u32..=4, 1u32..=4, 1usize..=16).prop_flat_map(|(tiles_x, tiles_y, colors)| {
+        let (width, height) = (tiles_x * 8, tiles_y * 8);
+        let pixels = prop::collection::vec(0..colors as u8, (width * height) as usize);
+        let palette = prop::collection::vec(
+            any::<(u8, u8, u8)>().prop_map(|(r, g, b)| Color::RGB { r, g, b }),
+            colors,
+        );
+        (Just(width), Just(height), pixels, palette)
+    })
+}
+
+fn convert(
+    (width, height, pixels, palette): &(u32, u32, Vec<u8>, Vec<Color>),
+    compression: Compression,
+) -> Bitmap {
+    BitmapBuilder::from_indexed(*width, *height, pixels, palette)
+        .unwrap()
+        .with_format(GfxFormat::Tile)
+        .with_bit_depth_override(BitDepth::Custom(8))
+        .with_map_layout(MapLayout::Flat)
+        .with_compression(compression)
+        .build()
+        .unwrap()
+}
+
+fn unpack(compressed: &[u8], expected: &[u8]) -> Vec<u8> {
+    let mut out = vec![0; expected.len()];
+    let size = decompress(compressed, &mut out).unwrap();
+    assert_eq!(size, expected.len());
+    out
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn round_trip(input in image()) {
+        let raw = convert(&input, Compression::Off);
+
+        for compression in [
+            Compression::LZ77,
+            Compression::Huffman,
+            Compression::RLE,
+            Compression::OffHeader,
+        ] {
+            let packed = convert(&input, compression);
+
+            prop_assert_eq!(unpack(&packed.gfx, &raw.gfx), raw.gfx.clone());
+            prop_assert_eq!(unpack(&packed.palette, &raw.palette), raw.palette.clone());
+            prop_assert_eq!(unpack(&packed.map, &raw.map), raw.map.clone());
+        }
+    }
+}
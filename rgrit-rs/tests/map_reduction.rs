@@ -0,0 +1,33 @@
+use rgrit_core::{Color, GfxFormat, MapReduction};
+use rgrit_rs::BitmapBuilder;
+
+fn image() -> BitmapBuilder {
+    let palette = [Color::RGB { r: 0, g: 0, b: 0 }; 16];
+    BitmapBuilder::from_indexed(16, 16, &[0; 256], &palette)
+        .unwrap()
+        .with_format(GfxFormat::Tile)
+}
+
+#[test]
+fn flips_and_palettes_need_tiles() {
+    for reduction in [
+        MapReduction {
+            flips: true,
+            ..MapReduction::NONE
+        },
+        MapReduction {
+            flips: true,
+            palettes: true,
+            ..MapReduction::NONE
+        },
+    ] {
+        let bitmap = image().with_map_reduction(reduction).build().unwrap();
+        assert_eq!(bitmap.spec.map_reduction, MapReduction::NONE);
+    }
+
+    let bitmap = image()
+        .with_map_reduction(MapReduction::TILES_FLIPS)
+        .build()
+        .unwrap();
+    assert_eq!(bitmap.spec.map_reduction, MapReduction::TILES_FLIPS);
+}
@@ -0,0 +1,201 @@
+use rgrit_core::{BitDepth, Color, GfxFormat, MapLayout, MapReduction, Transparency};
+use rgrit_rs::{BitmapBuilder, Error, Setting};
+
+fn image() -> BitmapBuilder {
+    let palette = [Color::RGB { r: 0, g: 0, b: 0 }; 16];
+    BitmapBuilder::from_indexed(16, 16, &[0; 256], &palette).unwrap()
+}
+
+fn settings(builder: BitmapBuilder) -> Vec<Setting> {
+    builder.validate().unwrap_err().settings()
+}
+
+#[test]
+fn defaults_are_valid() {
+    image().validate().unwrap();
+    image()
+        .with_format(GfxFormat::Tile)
+        .with_meta_width(2)
+        .with_meta_height(2)
+        .validate()
+        .unwrap();
+}
+
+#[test]
+fn conflicts() {
+    assert_eq!(
+        settings(
+            image()
+                .with_format(GfxFormat::Tile)
+                .with_bit_depth_override(BitDepth::A3I5)
+        ),
+        [Setting::BitDepth, Setting::Format]
+    );
+    assert_eq!(
+        settings(
+            image()
+                .with_format(GfxFormat::Tile)
+                .with_transparency(Transparency::Disabled)
+        ),
+        [Setting::Transparency, Setting::Format]
+    );
+    assert_eq!(
+        settings(
+            image()
+                .with_map(false)
+                .with_map_reduction(MapReduction::TILES)
+        ),
+        [Setting::Map, Setting::MapReduction]
+    );
+    assert_eq!(
+        settings(image().with_palette_start(32).with_palette_end(16)),
+        [Setting::PaletteEnd, Setting::PaletteStart]
+    );
+    assert_eq!(
+        settings(
+            image()
+                .with_map_layout(MapLayout::Affine)
+                .with_map_entry_format("16:i10".parse().unwrap())
+        ),
+        [Setting::MapEntryFormat, Setting::MapLayout]
+    );
+}
+
+#[test]
+fn palette_range() {
+    image()
+        .with_palette_start(240)
+        .with_palette_count(16)
+        .validate()
+        .unwrap();
+    assert!(matches!(
+        image().with_palette_start(256).validate(),
+        Err(Error::BadPaletteRange {
+            setting: Setting::PaletteStart,
+            value: 256
+        })
+    ));
+    assert!(matches!(
+        image().with_palette_end(257).validate(),
+        Err(Error::BadPaletteRange {
+            setting: Setting::PaletteEnd,
+            value: 257
+        })
+    ));
+    assert!(matches!(
+        image()
+            .with_palette_start(240)
+            .with_palette_count(17)
+            .validate(),
+        Err(Error::BadPaletteRange {
+            setting: Setting::PaletteCount,
+            value: 17
+        })
+    ));
+}
+
+#[test]
+fn palette_count_overflow() {
+    // start + count doesn't fit into a u16
+    assert!(matches!(
+        image()
+            .with_palette_start(255)
+            .with_palette_count(u16::MAX)
+            .validate(),
+        Err(Error::BadPaletteRange {
+            setting: Setting::PaletteCount,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn area() {
+    image()
+        .with_area_left(8)
+        .with_area_width(8)
+        .validate()
+        .unwrap();
+    assert!(matches!(
+        image().with_area_left(-1).validate(),
+        Err(Error::AreaOutOfBounds {
+            setting: Setting::AreaLeft,
+            value: -1,
+            size: 16
+        })
+    ));
+    assert!(matches!(
+        image().with_area_top(4).with_area_bottom(4).validate(),
+        Err(Error::AreaOutOfBounds {
+            setting: Setting::AreaBottom,
+            ..
+        })
+    ));
+    assert!(matches!(
+        image().with_area_left(8).with_area_width(9).validate(),
+        Err(Error::AreaOutOfBounds {
+            setting: Setting::AreaWidth,
+            value: 9,
+            size: 16
+        })
+    ));
+}
+
+#[test]
+fn area_overflow() {
+    // left + width doesn't fit into an i32
+    assert!(matches!(
+        image()
+            .with_area_left(8)
+            .with_area_width(i32::MAX)
+            .validate(),
+        Err(Error::AreaOutOfBounds {
+            setting: Setting::AreaWidth,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn tile_size() {
+    for (builder, setting, size) in [
+        (image().with_tile_width(0), Setting::TileWidth, 0),
+        (image().with_tile_height(0), Setting::TileHeight, 0),
+        (image().with_tile_width(12), Setting::TileWidth, 12),
+        (image().with_tile_height(3), Setting::TileHeight, 3),
+    ] {
+        let error = builder.with_format(GfxFormat::Tile).validate().unwrap_err();
+        assert!(
+            matches!(error, Error::BadTileSize { setting: s, size: n } if s == setting && n == size),
+            "{error}"
+        );
+    }
+
+    image()
+        .with_format(GfxFormat::Tile)
+        .with_tile_width(16)
+        .with_tile_height(8)
+        .validate()
+        .unwrap();
+    // Bitmaps aren't split into tiles, so the tile size doesn't matter
+    image().with_tile_width(3).validate().unwrap();
+}
+
+#[test]
+fn meta_size() {
+    assert!(matches!(
+        image().with_meta_width(0).validate(),
+        Err(Error::ZeroMetaSize(Setting::MetaWidth))
+    ));
+    assert!(matches!(
+        image()
+            .with_format(GfxFormat::Tile)
+            .with_meta_height(3)
+            .validate(),
+        Err(Error::BadMetaSize {
+            setting: Setting::MetaHeight,
+            size: 3,
+            tiles: 2
+        })
+    ));
+}